@@ -20,7 +20,19 @@ createApp({
               v-model="keyword"
               placeholder="输入你想搜索的内容"
               @keyup.enter="onClickSearch"
+              @input="onInputKeyword"
+              @blur="hideSuggestions"
             />
+            <!-- 输入建议 -->
+            <div v-if="suggestions.length > 0" class="absolute z-10 left-6 right-6 top-12 flex flex-col py-2 rounded-md shadow-lg bg-white text-sm">
+              <span
+                v-for="s in suggestions" :key="s.kind + s.text" @mousedown.prevent="onClickSuggestion(s)"
+                class="flex flex-row px-4 leading-7 cursor-pointer hover:bg-gray-100"
+              >
+                {{ s.text }}
+                <span class="ml-auto text-gray-400">{{ s.kind === 'term' ? s.count + ' 篇' : s.count + ' 个标题' }}</span>
+              </span>
+            </div>
             <div @click="onClickSearch" class="cursor-pointer grid place-items-center w-12 h-12 text-gray-400">
               <svg xmlns="http://www.w3.org/2000/svg" class="h-6 w-6" fill="none" viewBox="0 0 24 24" stroke="currentColor">
                 <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M21 21l-6-6m2-5a7 7 0 11-14 0 7 7 0 0114 0z" />
@@ -30,22 +42,85 @@ createApp({
 
           <!-- 筛选 -->
           <div class="flex flex-col gap-4 w-full">
-            <div class="flex flex-row gap-3 items-center text-sm">
+            <div v-if="result" class="flex flex-row flex-wrap gap-3 items-center text-sm">
               <span class="font-bold">类别</span>
-                <span
-                  v-for="v,i in allInfotypes" :key="i" @click="selectedInfotype = i"
-                  :class="['px-3 leading-6 rounded-full cursor-pointer', selectedInfotype === i ? 'text-white bg-blue-600' : 'text-gray-600 bg-gray-200']"
-                >
-                  {{ v }}
-                </span>
+              <span
+                @click="selectedInfotype = null"
+                :class="['px-3 leading-6 rounded-full cursor-pointer', selectedInfotype === null ? 'text-white bg-blue-600' : 'text-gray-600 bg-gray-200']"
+              >
+                全部
+              </span>
+              <span
+                v-for="f in result.facets.infotype" :key="f.value" @click="selectedInfotype = f.value"
+                :class="['px-3 leading-6 rounded-full cursor-pointer', selectedInfotype === f.value ? 'text-white bg-blue-600' : 'text-gray-600 bg-gray-200']"
+              >
+                {{ f.value }} <span class="opacity-60">{{ f.count }}</span>
+              </span>
             </div>
-            <div class="flex flex-row flex-wrap gap-3 items-center text-sm">
+            <div v-if="result" class="flex flex-row flex-wrap gap-3 items-center text-sm">
               <span class="font-bold">单位</span>
               <span
-                v-for="v,i in allUsers" :key="i" @click="selectedUser = i"
-                :class="['px-3 leading-6 rounded-full cursor-pointer', selectedUser === i ? 'text-white bg-blue-600' : 'text-gray-600 bg-gray-200']"
+                @click="selectedUser = null"
+                :class="['px-3 leading-6 rounded-full cursor-pointer', selectedUser === null ? 'text-white bg-blue-600' : 'text-gray-600 bg-gray-200']"
+              >
+                全部
+              </span>
+              <span
+                v-for="f in result.facets.user" :key="f.value" @click="selectedUser = f.value"
+                :class="['px-3 leading-6 rounded-full cursor-pointer', selectedUser === f.value ? 'text-white bg-blue-600' : 'text-gray-600 bg-gray-200']"
+              >
+                {{ f.value }} <span class="opacity-60">{{ f.count }}</span>
+              </span>
+            </div>
+            <div v-if="result" class="flex flex-row flex-wrap gap-3 items-center text-sm">
+              <span class="font-bold">年份</span>
+              <span
+                @click="selectedYear = null"
+                :class="['px-3 leading-6 rounded-full cursor-pointer', selectedYear === null ? 'text-white bg-blue-600' : 'text-gray-600 bg-gray-200']"
+              >
+                全部
+              </span>
+              <span
+                v-for="f in result.facets.year" :key="f.value" @click="selectedYear = f.value"
+                :class="['px-3 leading-6 rounded-full cursor-pointer', selectedYear === f.value ? 'text-white bg-blue-600' : 'text-gray-600 bg-gray-200']"
+              >
+                {{ f.value }} <span class="opacity-60">{{ f.count }}</span>
+              </span>
+            </div>
+            <div class="flex flex-row gap-3 items-center text-sm">
+              <span class="font-bold">顺序</span>
+              <span
+                v-for="v,i in allSorts" :key="i" @click="selectedSort = i"
+                :class="['px-3 leading-6 rounded-full cursor-pointer', selectedSort === i ? 'text-white bg-blue-600' : 'text-gray-600 bg-gray-200']"
+              >
+                {{ v.name }}
+              </span>
+            </div>
+            <div class="flex flex-row gap-3 items-center text-sm">
+              <span class="font-bold">时效</span>
+              <span
+                v-for="v,i in allDecays" :key="i" @click="selectedDecay = i"
+                :class="['px-3 leading-6 rounded-full cursor-pointer', selectedDecay === i ? 'text-white bg-blue-600' : 'text-gray-600 bg-gray-200']"
+              >
+                {{ v.name }}
+              </span>
+            </div>
+            <div class="flex flex-row gap-3 items-center text-sm">
+              <span class="font-bold">排序</span>
+              <span
+                v-for="v,i in allRankings" :key="i" @click="selectedRanking = i"
+                :class="['px-3 leading-6 rounded-full cursor-pointer', selectedRanking === i ? 'text-white bg-blue-600' : 'text-gray-600 bg-gray-200']"
               >
-                {{ v }}
+                {{ v.name }}
+              </span>
+            </div>
+            <div class="flex flex-row gap-3 items-center text-sm">
+              <span class="font-bold">匹配</span>
+              <span
+                v-for="v,i in allOperators" :key="i" @click="selectedOperator = i"
+                :class="['px-3 leading-6 rounded-full cursor-pointer', selectedOperator === i ? 'text-white bg-blue-600' : 'text-gray-600 bg-gray-200']"
+              >
+                {{ v.name }}
               </span>
             </div>
           </div>
@@ -56,7 +131,17 @@ createApp({
       <!-- 搜索结果 -->
       <div class="flex flex-col items-center">
         <div v-if="result" class="flex flex-col py-8 gap-8 w-4/5 mx-auto max-w-screen-lg">
-          <p class="text-sm text-gray-500">找到 <b>{{ result.total_hits }}</b> 条结果 （用时 <b>{{ result.time }}</b> 毫秒）</p>
+          <p class="text-sm text-gray-500">找到 <template v-if="result.total_hits_relation === 'gte'">至少 </template><b>{{ result.total_hits }}</b> 条结果 （用时 <b>{{ result.time }}</b> 毫秒）</p>
+
+          <p v-if="result.original_keyword" class="text-md text-gray-600">
+            以下显示的是“<b>{{ result.keyword }}</b>”的搜索结果，仍然搜索：
+            <a class="text-blue-700 cursor-pointer" @click="onClickKeyword(result.original_keyword, false)">{{ result.original_keyword }}</a>
+          </p>
+          <p v-else-if="result.did_you_mean" class="text-md text-gray-600">
+            您是不是要找：
+            <a class="text-blue-700 cursor-pointer" @click="onClickKeyword(result.did_you_mean)">{{ result.did_you_mean }}</a>
+          </p>
+          <p v-if="result.missing_terms.length > 0" class="text-sm text-gray-500">以下词语未收录，搜索时已忽略：{{ result.missing_terms.join('、') }}</p>
 
           <p v-if="result.total_hits === 0" class="text-md text-gray-600">找不到和您查询的“{{ result.keyword }}”相符的内容或信息。</p>
 
@@ -65,12 +150,15 @@ createApp({
             <div class="flex flex-row items-center gap-4">
               <!-- 标题 -->
               <a :href="hit.doc.url" target="_blank">
-                <span class="text-lg text-blue-700">{{ formatTitle(hit.doc.title) }}</span>
+                <span v-if="hit.highlight" class="text-lg text-blue-700" v-html="hit.highlight.title[0]" />
+                <span v-else class="text-lg text-blue-700">{{ formatTitle(hit.doc.title) }}</span>
               </a>
               <!-- 得分 -->
-              <span class="text-xl text-red-700">{{ hit.score.toFixed(2) }}</span>
+              <span class="text-xl text-red-700 cursor-pointer" title="得分详情" @click="hit.showExplain = !hit.showExplain">{{ hit.score.toFixed(2) }}</span>
               <!-- 图文按钮 -->
               <button @click="hit.raw = hit.raw ? false : true" :class="['px-3 py-1 rounded-full text-sm cursor-pointer', hit.raw ? 'text-white bg-blue-600' : 'text-gray-600 bg-gray-200']">图文</button>
+              <!-- 相关通知按钮 -->
+              <button @click="onClickSimilar(hit)" :class="['px-3 py-1 rounded-full text-sm cursor-pointer', hit.similar ? 'text-white bg-blue-600' : 'text-gray-600 bg-gray-200']">相关</button>
               <!-- 时间 -->
               <span class="ml-auto text-sm text-gray-400">{{ formatTime(hit.doc.time) }}</span>
             </div>
@@ -80,9 +168,35 @@ createApp({
               <div v-if="hit.raw" class="border h-96 rounded-md overflow-scroll">
                 <article class="szu m-2" v-html="hit.doc.html" />
               </div>
+              <p v-else-if="hit.highlight && hit.highlight.text.length > 0" class="line-clamp-3" v-html="hit.highlight.text.join(' … ')" />
               <p v-else class="line-clamp-3">{{ hit.doc.text }}</p>
             </div>
 
+            <!-- 得分详情 -->
+            <div v-if="hit.showExplain && hit.explain" class="text-xs text-gray-500 leading-5">
+              <p>文本得分 {{ hit.explain.text_score.toFixed(4) }}（{{ hit.explain.ranking }}）<span v-if="hit.explain.decay">，时间衰减 ×{{ hit.explain.decay.factor.toFixed(4) }}</span></p>
+              <table>
+                <tr class="text-left"><th class="pr-4">词项</th><th class="pr-4">词频</th><th class="pr-4">IDF</th><th class="pr-4">权重</th><th>贡献</th></tr>
+                <tr v-for="t in hit.explain.terms" :key="t.id">
+                  <td class="pr-4">{{ t.term }}</td>
+                  <td class="pr-4">{{ t.tf }}</td>
+                  <td class="pr-4">{{ t.idf.toFixed(4) }}</td>
+                  <td class="pr-4">{{ t.weight.toFixed(4) }}</td>
+                  <td>{{ t.contribution.toFixed(4) }}</td>
+                </tr>
+              </table>
+            </div>
+
+            <!-- 相关通知 -->
+            <div v-if="hit.similar" class="flex flex-col text-sm leading-6 pl-4 border-l-2 border-gray-200">
+              <span class="text-gray-400">相关通知</span>
+              <span v-if="hit.similar.length === 0" class="text-gray-500">没有相关通知</span>
+              <a v-for="s in hit.similar" :key="s.id" :href="s.doc.url" target="_blank" class="flex flex-row gap-4">
+                <span class="text-blue-700">{{ formatTitle(s.doc.title) }}</span>
+                <span class="ml-auto text-gray-400">{{ formatTime(s.doc.time) }}</span>
+              </a>
+            </div>
+
             <!-- 附件 -->
             <div v-if="hit.doc.attachments.length > 0" class="flex flex-col text-sm text-red-500 leading-6">
               <a v-for="attachment in hit.doc.attachments" :href="attachment.url" target="_blank">
@@ -101,121 +215,84 @@ createApp({
     </div>
   `,
   setup() {
-    const allInfotypes = [
-      '全部',
-      '教务',
-      '科研',
-      '行政',
-      '学工',
-      '会议',
-      '讲座',
-      '生活',
+    const allRankings = [
+      { name: 'TF-IDF', value: 'tfidf' },
+      { name: 'BM25', value: 'bm25' },
+      { name: 'BM25F', value: 'bm25f' },
     ]
-    const allUsers = [
-      '全部',
-      '党政办公室',
-      '组织部',
-      '统战部',
-      '宣传部',
-      '纪检（监察）室',
-      '校工会',
-      '妇女委员会',
-      '校团委',
-      '教务部',
-      '招生办公室',
-      '创新创业教育中心',
-      '继续教育管理办公室',
-      '研究生院',
-      '党委研工部',
-      '发展规划部',
-      '社会科学部',
-      '学报社科版',
-      '科学技术部',
-      '学报理工版',
-      '学生部',
-      '党委学工部',
-      '国际交流与合作部',
-      '人力资源部',
-      '党委教师工作部',
-      '计划财务部',
-      '招投标管理中心',
-      '实验室与国有资产管理部',
-      '审计室',
-      '后勤保障部',
-      '后勤保障部党委',
-      '安全保卫部',
-      '离退休办公室',
-      '校友联络部',
-      '教育发展基金会',
-      '机关党委',
-      '丽湖校区管理办公室',
-      '教育学部',
-      '艺术学部',
-      '医学部',
-      '马克思主义学院',
-      '经济学院',
-      '法学院',
-      '心理学院',
-      '体育学院',
-      '人文学院',
-      '外国语学院',
-      '传播学院',
-      '数学与统计学院',
-      '物理与光电工程学院',
-      '化学与环境工程学院',
-      '生命与海洋科学学院',
-      '机电与控制工程学院',
-      '材料学院',
-      '电子与信息工程学院',
-      '计算机与软件学院',
-      '建筑与城市规划学院',
-      '土木与交通工程学院',
-      '管理学院',
-      '政府管理学院',
-      '高等研究院',
-      '金融科技学院',
-      '国际交流学院',
-      '继续教育学院',
-      '图书馆',
-      '图书馆党总支',
-      '档案馆',
-      '信息中心',
-      '信息中心党总支',
-      '资产经营公司',
-      '技术转化中心',
-      '深大总医院',
-      '深大附属华南医院',
-      '校医院',
-      '附属教育集团',
-      '深大附属中学',
-      '深大附属实验中学',
-      '中国经济特区研究中心',
-      '港澳基本法研究中心',
-      '文化产业研究院',
-      '美学与文艺批评研究院',
-      '饶宗颐文化研究院',
-      '中国海外利益研究院',
-      '微纳光电子学研究院',
-      '创新技术研究院',
-      '大数据系统计算技术国家工程实验室',
-      '心理健康教育与咨询中心',
-      '人工智能与数字经济广东省实验室（深圳）',
-      '深圳香蜜湖国际金融科技研究院',
+
+    const allOperators = [
+      { name: '全部词', value: 'and' },
+      { name: '任意词', value: 'or' },
+    ]
+
+    const allSorts = [
+      { name: '相关度', value: 'relevance' },
+      { name: '最新', value: 'newest' },
+      { name: '最早', value: 'oldest' },
+    ]
+
+    const allDecays = [
+      { name: '默认', value: undefined },
+      { name: '近期优先', value: { function: 'exp' } },
+      { name: '不考虑', value: { function: 'none' } },
     ]
 
     const keyword = ref('')
-    const selectedInfotype = ref(0)
-    const selectedUser = ref(0)
+    // 类别、单位从搜索结果的分面统计中选择，null 表示全部
+    const selectedInfotype = ref(null)
+    const selectedUser = ref(null)
+    // 年份转为发布时间范围
+    const selectedYear = ref(null)
+    const selectedSort = ref(0)
+    const selectedDecay = ref(0)
+    const selectedRanking = ref(0)
+    const selectedOperator = ref(0)
     const offset = ref(0)
     const limit = ref(10)
     const result = ref()
     const searching = ref(false)
 
+    const suggestions = ref([])
+    let suggestTimer = null
+
+    const onInputKeyword = () => {
+      clearTimeout(suggestTimer)
+      suggestTimer = setTimeout(async () => {
+        const q = keyword.value
+        if (!q.trim()) {
+          suggestions.value = []
+          return
+        }
+        const res = await fetch(`/suggest?q=${encodeURIComponent(q)}`)
+        const { terms, titles } = await res.json()
+        if (q !== keyword.value) return
+        suggestions.value = [
+          ...terms.map((s) => ({ ...s, kind: 'term' })),
+          ...titles.map((s) => ({ ...s, kind: 'title' })),
+        ]
+      }, 150)
+    }
+
+    const hideSuggestions = () => {
+      clearTimeout(suggestTimer)
+      suggestions.value = []
+    }
+
+    const onClickSuggestion = (s) => {
+      keyword.value = s.text
+      hideSuggestions()
+      doSearch(true)
+    }
+
     const hasNextPage = computed(
       () => result.value && result.value.total_hits > offset.value + limit.value
     )
     const hasPrevPage = computed(() => offset.value > 0)
 
+    // 为 false 时不自动纠错
+    let autoCorrect = true
+
     const doSearch = async (first) => {
       if (first) {
         offset.value = 0
@@ -233,14 +310,19 @@ createApp({
             offset: offset.value,
             limit: limit.value,
             filter: {
-              infotype:
-                selectedInfotype.value === 0
-                  ? undefined
-                  : allInfotypes[selectedInfotype.value],
-              user:
-                selectedUser.value === 0
-                  ? undefined
-                  : allUsers[selectedUser.value],
+              infotype: selectedInfotype.value ?? undefined,
+              user: selectedUser.value ?? undefined,
+              year: selectedYear.value ?? undefined,
+            },
+            sort: allSorts[selectedSort.value].value,
+            decay: allDecays[selectedDecay.value].value,
+            explain: true,
+            auto_correct: autoCorrect,
+            ranking: allRankings[selectedRanking.value].value,
+            operator: allOperators[selectedOperator.value].value,
+            highlight: {
+              pre_tag: '<em class="not-italic text-red-600">',
+              post_tag: '</em>',
             },
           }),
         })
@@ -250,11 +332,24 @@ createApp({
       }
     }
 
-    watch([selectedInfotype, selectedUser], () => {
+    watch(
+      [selectedInfotype, selectedUser, selectedYear, selectedSort, selectedDecay, selectedRanking, selectedOperator],
+      () => {
+        doSearch(true)
+      }
+    )
+
+    const onClickSearch = () => {
+      hideSuggestions()
+      autoCorrect = true
       doSearch(true)
-    })
+    }
 
-    const onClickSearch = () => doSearch(true)
+    const onClickKeyword = (k, correct = true) => {
+      keyword.value = k
+      autoCorrect = correct
+      doSearch(true)
+    }
 
     const onClickNext = () => {
       if (!hasNextPage.value) return
@@ -268,6 +363,15 @@ createApp({
       doSearch(false)
     }
 
+    const onClickSimilar = async (hit) => {
+      if (hit.similar) {
+        hit.similar = null
+        return
+      }
+      const res = await fetch(`/similar/${hit.id}`)
+      hit.similar = (await res.json()).hits ?? []
+    }
+
     const formatTitle = (title) => {
       if (title.length > 30) {
         return title.slice(0, 30) + '...'
@@ -277,15 +381,23 @@ createApp({
     }
 
     const formatTime = (time) => {
+      if (!time) return '未知时间'
       return dayjs(time).format('YYYY年MM月DD日 HH:mm:ss')
     }
 
     return {
-      allInfotypes,
-      allUsers,
+      allRankings,
+      allOperators,
+      allSorts,
+      allDecays,
       keyword,
       selectedInfotype,
       selectedUser,
+      selectedYear,
+      selectedSort,
+      selectedDecay,
+      selectedRanking,
+      selectedOperator,
       offset,
       limit,
       result,
@@ -293,8 +405,14 @@ createApp({
       hasNextPage,
       hasPrevPage,
       onClickSearch,
+      onClickKeyword,
+      suggestions,
+      onInputKeyword,
+      hideSuggestions,
+      onClickSuggestion,
       onClickNext,
       onClickPrev,
+      onClickSimilar,
       formatTitle,
       formatTime,
     }
@@ -0,0 +1,112 @@
+use crate::{
+    core::UpdateStats,
+    dataset::{Dataset, Doc},
+    error::AppError,
+    AppState,
+};
+use axum::{
+    extract::State,
+    http::{header::AUTHORIZATION, HeaderMap},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DeleteParams {
+    /// 要删除的文档 URL
+    pub urls: Vec<String>,
+}
+
+/// 检查 `Authorization: Bearer <admin_token>`；没有配置 `admin_token` 时拒绝所有请求
+fn authorize(state: &AppState, headers: &HeaderMap) -> Result<(), AppError> {
+    let Some(token) = &state.config.admin_token else {
+        return Err(AppError::Forbidden);
+    };
+    let bearer = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if bearer != Some(token.as_str()) {
+        return Err(AppError::Unauthorized);
+    }
+    Ok(())
+}
+
+/// 在阻塞线程中修改数据集和索引，完成后保存索引
+///
+/// 先锁数据集再锁索引，与搜索的加锁顺序一致
+async fn update(
+    state: AppState,
+    f: impl FnOnce(&mut crate::core::Core, &mut Dataset) -> UpdateStats + Send + 'static,
+) -> Result<Json<UpdateStats>, AppError> {
+    let dataset_fingerprint = Dataset::fingerprint().await?;
+    let stats = tokio::task::spawn_blocking(move || {
+        let mut dataset = state.dataset.blocking_write();
+        let mut core = state.core.blocking_write();
+        let stats = f(&mut core, &mut dataset);
+        tracing::info!("[Admin] 更新完成: {:?}", stats);
+
+        if stats != UpdateStats::default() {
+            if let Err(e) = core.save(
+                &dataset,
+                dataset_fingerprint,
+                state.config.fingerprint(),
+                &state.config.index_path,
+            ) {
+                tracing::warn!("[Core] 保存索引失败: {:#}", e);
+            }
+        }
+        stats
+    })
+    .await?;
+
+    Ok(Json(stats))
+}
+
+/// `POST /admin/docs`：添加文档，URL 已存在时替换
+pub async fn upsert_docs(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(docs): Json<Vec<Doc>>,
+) -> Result<Json<UpdateStats>, AppError> {
+    authorize(&state, &headers)?;
+    tracing::info!("[Admin] 添加 {} 个文档", docs.len());
+    update(state, move |core, dataset| core.upsert(dataset, docs)).await
+}
+
+/// `DELETE /admin/docs`：按 URL 删除文档
+pub async fn delete_docs(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(DeleteParams { urls }): Json<DeleteParams>,
+) -> Result<Json<UpdateStats>, AppError> {
+    authorize(&state, &headers)?;
+    tracing::info!("[Admin] 删除 {} 个文档", urls.len());
+    update(state, move |core, dataset| core.delete_urls(dataset, &urls)).await
+}
+
+/// `POST /admin/sync`：重新读取数据集目录，同步新增、修改和删除的文档
+pub async fn sync(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<UpdateStats>, AppError> {
+    authorize(&state, &headers)?;
+    tracing::info!("[Admin] 同步数据集");
+    let latest = Dataset::load().await?;
+    update(state, move |core, dataset| core.sync(dataset, latest)).await
+}
+
+/// `POST /admin/dict/reload`：重新读取 jieba 用户词典，重新分词受影响的文档
+pub async fn reload_dict(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+) -> Result<Json<UpdateStats>, AppError> {
+    authorize(&state, &headers)?;
+    tracing::info!("[Admin] 重新读取用户词典");
+    let mut analyzer = state.config.analyzer.clone();
+    analyzer.load_user_dicts().await?;
+    update(state, move |core, dataset| {
+        core.reload_user_dict(dataset, &analyzer)
+    })
+    .await
+}
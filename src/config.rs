@@ -0,0 +1,300 @@
+use crate::core::{Decay, PerField};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+/// 配置文件路径
+const CONFIG_PATH: &str = "./config.json";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Config {
+    /// 索引文件路径
+    pub index_path: String,
+    /// 管理接口的令牌，不设置时禁用管理接口；不计入配置指纹
+    #[serde(skip_serializing)]
+    pub admin_token: Option<String>,
+    pub analyzer: AnalyzerConfig,
+    pub bm25: Bm25Config,
+    pub bm25f: Bm25fConfig,
+    /// 段合并，只影响索引的组织方式，不计入配置指纹
+    #[serde(skip_serializing)]
+    pub merge: MergeConfig,
+    /// 按发布时间衰减得分的默认参数，可被搜索参数覆盖；只影响搜索，不计入配置指纹
+    #[serde(skip_serializing)]
+    pub decay: Decay,
+    /// 构建索引（分词、计数、计算 TF-IDF）的线程数，0 表示与 CPU 核心数相同；
+    /// 不影响索引内容，不计入配置指纹
+    #[serde(skip_serializing)]
+    pub threads: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            index_path: "./index.bin".to_owned(),
+            admin_token: None,
+            analyzer: AnalyzerConfig::default(),
+            bm25: Bm25Config::default(),
+            bm25f: Bm25fConfig::default(),
+            merge: MergeConfig::default(),
+            decay: Decay::default(),
+            threads: 0,
+        }
+    }
+}
+
+/// 分析器，变化时需要重新构建索引（用户词典除外）
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct AnalyzerConfig {
+    pub tokenizer: TokenizerKind,
+    pub normalize: NormalizeConfig,
+    /// 英文是否提取词干
+    pub stemming: bool,
+    pub stopwords: StopwordsConfig,
+    /// jieba 用户词典文件，每行 `词 [词频] [词性]`，`#` 开头的行为注释；
+    /// 词典变化时只重新分词受影响的文档，不计入配置指纹
+    #[serde(skip_serializing)]
+    pub user_dicts: Vec<String>,
+    /// 从 `user_dicts` 读取的词（读取配置时填充）
+    #[serde(skip)]
+    pub user_words: Vec<UserWord>,
+}
+
+impl Default for AnalyzerConfig {
+    fn default() -> Self {
+        Self {
+            tokenizer: TokenizerKind::Jieba,
+            normalize: NormalizeConfig::default(),
+            stemming: true,
+            stopwords: StopwordsConfig::default(),
+            user_dicts: vec![],
+            user_words: vec![],
+        }
+    }
+}
+
+impl AnalyzerConfig {
+    /// 读取 jieba 用户词典文件，后面的文件中的词覆盖前面的
+    pub async fn load_user_dicts(&mut self) -> anyhow::Result<()> {
+        self.user_words.clear();
+        for file in &self.user_dicts {
+            let s = tokio::fs::read_to_string(file)
+                .await
+                .with_context(|| format!("读取用户词典 {} 失败", file))?;
+            let words = parse_user_dict(&s);
+            tracing::info!("[Config] 已读取用户词典 {}，{} 个词", file, words.len());
+            self.user_words.extend(words);
+        }
+        Ok(())
+    }
+}
+
+/// 用户词典中的词
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct UserWord {
+    pub word: String,
+    /// 词频，不设置时取刚好能使这个词不被切开的词频
+    pub freq: Option<usize>,
+    /// 词性
+    pub tag: Option<String>,
+}
+
+/// 解析用户词典：每行 `词 [词频] [词性]`，词频可以省略（第二列不是整数时视为词性）
+pub fn parse_user_dict(s: &str) -> Vec<UserWord> {
+    parse_word_list(s)
+        .iter()
+        .map(|line| {
+            let mut columns = line.split_whitespace();
+            let word = columns.next().unwrap_or_default().to_owned();
+            let mut next = columns.next();
+            let freq = next.and_then(|x| x.parse().ok());
+            if freq.is_some() {
+                next = columns.next();
+            }
+            UserWord {
+                word,
+                freq,
+                tag: next.map(str::to_owned),
+            }
+        })
+        .collect()
+}
+
+/// 切分之前的文本规范化，索引和搜索时相同
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct NormalizeConfig {
+    /// Unicode 兼容等价规范化（NFKC），如全角字母、数字和标点转为半角，`①` 转为 `1`
+    pub nfkc: bool,
+    /// 全角 ASCII 字符和全角空格转为半角（已包含在 NFKC 中，不使用 NFKC 时单独设置）
+    pub width: bool,
+    /// 繁体转简体
+    pub traditional_to_simplified: bool,
+    /// Unicode 大小写折叠（不只是 ASCII）
+    pub case_fold: bool,
+}
+
+impl Default for NormalizeConfig {
+    fn default() -> Self {
+        Self {
+            nfkc: true,
+            width: true,
+            traditional_to_simplified: false,
+            case_fold: true,
+        }
+    }
+}
+
+/// 停用词，索引和搜索时都会去掉
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StopwordsConfig {
+    /// 是否使用内置的中英文停用词表
+    pub builtin: bool,
+    /// 是否去掉不含字母和数字的词（标点、符号）
+    pub punctuation: bool,
+    /// 停用词表文件，每行一个词，`#` 开头的行为注释
+    pub files: Vec<String>,
+    /// 从 `files` 读取的停用词（读取配置时填充），计入配置指纹，文件内容变化时重新构建索引
+    #[serde(skip_deserializing)]
+    pub words: Vec<String>,
+}
+
+impl Default for StopwordsConfig {
+    fn default() -> Self {
+        Self {
+            builtin: true,
+            punctuation: true,
+            files: vec![],
+            words: vec![],
+        }
+    }
+}
+
+impl StopwordsConfig {
+    /// 读取停用词表文件
+    pub async fn load(&mut self) -> anyhow::Result<()> {
+        self.words.clear();
+        for file in &self.files {
+            let s = tokio::fs::read_to_string(file)
+                .await
+                .with_context(|| format!("读取停用词表 {} 失败", file))?;
+            let words = parse_word_list(&s);
+            tracing::info!("[Config] 已读取停用词表 {}，{} 个词", file, words.len());
+            self.words.extend(words);
+        }
+        Ok(())
+    }
+}
+
+/// 解析词表：每行一个词，忽略空行和 `#` 开头的注释
+pub fn parse_word_list(s: &str) -> Vec<String> {
+    s.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_owned)
+        .collect()
+}
+
+/// 切分方式
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenizerKind {
+    /// jieba 搜索模式
+    #[default]
+    Jieba,
+    /// 汉字按相邻两个字切分，不需要词典
+    Bigram,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Bm25Config {
+    pub k1: f64,
+    pub b: f64,
+}
+
+impl Default for Bm25Config {
+    fn default() -> Self {
+        Self { k1: 1.2, b: 0.75 }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Bm25fConfig {
+    pub k1: f64,
+    /// 每个字段的长度归一化参数
+    pub b: PerField<f64>,
+    /// 每个字段的默认权重，可被搜索参数覆盖；只影响搜索，不计入配置指纹
+    #[serde(skip_serializing)]
+    pub boost: PerField<f64>,
+}
+
+impl Default for Bm25fConfig {
+    fn default() -> Self {
+        Self {
+            k1: 1.2,
+            b: PerField {
+                title: 0.5,
+                text: 0.75,
+                attachments: 0.5,
+            },
+            boost: PerField {
+                title: 3.0,
+                text: 1.0,
+                attachments: 1.5,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MergeConfig {
+    /// 同一层的段数达到多少时合并
+    pub segments_per_tier: usize,
+    /// 第 0 层的段大小上限（文档数）
+    pub floor_segment_size: usize,
+    /// 段中已删除文档的比例超过多少时重写
+    pub max_deleted_ratio: f64,
+    /// 后台检查的间隔（秒）
+    pub interval_secs: u64,
+}
+
+impl Default for MergeConfig {
+    fn default() -> Self {
+        Self {
+            segments_per_tier: 4,
+            floor_segment_size: 100,
+            max_deleted_ratio: 0.3,
+            interval_secs: 10,
+        }
+    }
+}
+
+impl Config {
+    /// 读取 `./config.json`，不存在时使用默认配置
+    pub async fn load() -> anyhow::Result<Self> {
+        match tokio::fs::read_to_string(CONFIG_PATH).await {
+            Ok(s) => {
+                let mut config: Self = serde_json::from_str(&s)?;
+                tracing::info!("[Config] 已读取配置 {}", CONFIG_PATH);
+                config.analyzer.stopwords.load().await?;
+                config.analyzer.load_user_dicts().await?;
+                Ok(config)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                tracing::info!("[Config] 未找到 {}，使用默认配置", CONFIG_PATH);
+                Ok(Self::default())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 配置指纹，配置变化时已保存的索引失效
+    pub fn fingerprint(&self) -> u32 {
+        crc32fast::hash(&serde_json::to_vec(self).unwrap())
+    }
+}
@@ -0,0 +1,388 @@
+use crate::{
+    config::{AnalyzerConfig, TokenizerKind, UserWord},
+    core::{StopwordFilter, UnicodeNormalizer},
+};
+use jieba_rs::{Jieba, TokenizeMode};
+use rust_stemmers::{Algorithm, Stemmer};
+
+/// 分词结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    /// 词项（经过词项过滤器处理，如英文已转小写并提取词干）
+    pub term: String,
+    /// 在原文中的起始位置（字符）
+    pub start: usize,
+    /// 在原文中的结束位置（字符）
+    pub end: usize,
+    /// 词的位置，`cut_for_search` 切出的子词与所在的词位置相同，空白不占位置
+    pub position: usize,
+}
+
+/// 每个位置上最长的词（去掉搜索模式切出的子词），按位置排列
+///
+/// 子词与所在的词位置相同，短语匹配时只有完整的词才能判断是否相邻
+/// * `tokens`: 分词结果，位置递增
+pub fn full_words(tokens: &[Token]) -> impl Iterator<Item = &Token> {
+    tokens
+        .chunk_by(|a, b| a.position == b.position)
+        .filter_map(|words| words.iter().max_by_key(|t| t.end - t.start))
+}
+
+/// 分析器：把文本转换为词项，索引和搜索使用同一个分析器
+pub trait Analyzer: Send + Sync {
+    /// 分词，并记录每个词在原文中的位置
+    fn analyze(&self, text: &str) -> Vec<Token>;
+
+    /// 把单个词转换为词项（不切分），被过滤掉时返回 `None`
+    fn normalize(&self, word: &str) -> Option<String>;
+
+    /// 分词，同时返回被删除的词项（如停用词，可以重复），用于统计；默认不删除
+    fn analyze_with_removed(&self, text: &str) -> (Vec<Token>, Vec<String>) {
+        (self.analyze(text), vec![])
+    }
+}
+
+/// 字符过滤器，在切分之前处理文本
+pub trait CharFilter: Send + Sync {
+    /// * `chars`: 文本的字符及其在原文中的位置（字符）
+    /// * `returns`: 转换后的字符，每个字符带上它来自的原文位置；一个字符可以变为零个或多个字符
+    fn filter(&self, chars: Vec<(char, usize)>) -> Vec<(char, usize)>;
+}
+
+/// 切分器，位置为切分后的文本中的字符位置
+pub trait Tokenizer: Send + Sync {
+    fn tokenize(&self, text: &str) -> Vec<Token>;
+}
+
+/// 词项过滤器，可以修改、删除或增加词
+pub trait TokenFilter: Send + Sync {
+    fn filter(&self, tokens: Vec<Token>) -> Vec<Token>;
+
+    /// 同 [`TokenFilter::filter`]，并把删除的词项加入 `removed`
+    fn filter_with_removed(&self, tokens: Vec<Token>, removed: &mut Vec<String>) -> Vec<Token> {
+        let _ = removed;
+        self.filter(tokens)
+    }
+}
+
+/// 由字符过滤器、切分器和词项过滤器组成的分析器
+pub struct AnalyzerChain {
+    char_filters: Vec<Box<dyn CharFilter>>,
+    tokenizer: Box<dyn Tokenizer>,
+    token_filters: Vec<Box<dyn TokenFilter>>,
+}
+
+impl Default for AnalyzerChain {
+    /// jieba 搜索模式切分，英文转小写、去掉停用词并提取词干
+    fn default() -> Self {
+        Self::from_config(&AnalyzerConfig::default())
+    }
+}
+
+impl AnalyzerChain {
+    pub fn new(tokenizer: impl Tokenizer + 'static) -> Self {
+        Self {
+            char_filters: vec![],
+            tokenizer: Box::new(tokenizer),
+            token_filters: vec![],
+        }
+    }
+
+    /// 按配置创建
+    pub fn from_config(config: &AnalyzerConfig) -> Self {
+        let mut chain = match config.tokenizer {
+            TokenizerKind::Jieba => Self::new(JiebaTokenizer::with_user_dict(&config.user_words)),
+            TokenizerKind::Bigram => Self::new(CjkBigramTokenizer),
+        };
+        let normalizer = UnicodeNormalizer::from_config(&config.normalize);
+        if !normalizer.is_empty() {
+            chain = chain.char_filter(normalizer);
+        }
+        chain = chain.token_filter(AsciiLowercaseFilter);
+        let stopwords = StopwordFilter::from_config(&config.stopwords);
+        if !stopwords.is_empty() {
+            chain = chain.token_filter(stopwords);
+        }
+        if config.stemming {
+            chain = chain.token_filter(EnglishStemFilter::new());
+        }
+        chain
+    }
+
+    /// 追加字符过滤器
+    pub fn char_filter(mut self, filter: impl CharFilter + 'static) -> Self {
+        self.char_filters.push(Box::new(filter));
+        self
+    }
+
+    /// 追加词项过滤器
+    pub fn token_filter(mut self, filter: impl TokenFilter + 'static) -> Self {
+        self.token_filters.push(Box::new(filter));
+        self
+    }
+
+    /// 依次应用字符过滤器，返回过滤后的文本（切分器看到的文本），以及每个字符在原文中的位置
+    pub fn filter_chars(&self, text: &str) -> (String, Vec<usize>) {
+        let chars = text
+            .chars()
+            .enumerate()
+            .map(|(i, c)| (c, i))
+            .collect::<Vec<_>>();
+        self.char_filters
+            .iter()
+            .fold(chars, |chars, filter| filter.filter(chars))
+            .into_iter()
+            .unzip()
+    }
+
+    fn filter_tokens(&self, tokens: Vec<Token>, removed: &mut Vec<String>) -> Vec<Token> {
+        self.token_filters.iter().fold(tokens, |tokens, filter| {
+            filter.filter_with_removed(tokens, removed)
+        })
+    }
+}
+
+impl Analyzer for AnalyzerChain {
+    fn analyze(&self, text: &str) -> Vec<Token> {
+        self.analyze_with_removed(text).0
+    }
+
+    fn analyze_with_removed(&self, text: &str) -> (Vec<Token>, Vec<String>) {
+        let mut removed = vec![];
+        if self.char_filters.is_empty() {
+            let tokens = self.filter_tokens(self.tokenizer.tokenize(text), &mut removed);
+            return (tokens, removed);
+        }
+
+        // 切分后的位置映射回原文
+        let (filtered, origin) = self.filter_chars(text);
+        let mut tokens = self.tokenizer.tokenize(&filtered);
+        for token in &mut tokens {
+            if token.start < token.end {
+                token.start = origin[token.start];
+                token.end = origin[token.end - 1] + 1;
+            }
+        }
+        (self.filter_tokens(tokens, &mut removed), removed)
+    }
+
+    fn normalize(&self, word: &str) -> Option<String> {
+        let (filtered, _) = self.filter_chars(word);
+        let len = filtered.chars().count();
+        let token = Token {
+            term: filtered,
+            start: 0,
+            end: len,
+            position: 0,
+        };
+        self.filter_tokens(vec![token], &mut vec![])
+            .into_iter()
+            .next()
+            .map(|t| t.term)
+    }
+}
+
+/// jieba 搜索模式切分，长词之前会先输出其中的子词，空白不输出
+pub struct JiebaTokenizer {
+    jieba: Jieba,
+}
+
+impl JiebaTokenizer {
+    pub fn new() -> Self {
+        Self {
+            jieba: Jieba::new(),
+        }
+    }
+
+    /// 在默认词典之外加入用户词典中的词
+    pub fn with_user_dict(words: &[UserWord]) -> Self {
+        let mut jieba = Jieba::new();
+        for w in words {
+            jieba.add_word(&w.word, w.freq, w.tag.as_deref());
+        }
+        Self { jieba }
+    }
+}
+
+impl Default for JiebaTokenizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tokenizer for JiebaTokenizer {
+    fn tokenize(&self, text: &str) -> Vec<Token> {
+        let words = self.jieba.tokenize(text, TokenizeMode::Search, true);
+
+        let mut tokens = Vec::with_capacity(words.len());
+        let mut position = 0;
+        for (i, word) in words.iter().enumerate() {
+            // 搜索模式下，子词排在所在的词之前，且与之重叠
+            // 后一个词的起点不小于当前词的终点时，当前词才是完整的词
+            let is_word = words.get(i + 1).is_none_or(|next| next.start >= word.end);
+
+            if word.word.chars().all(|c| c.is_whitespace()) {
+                continue; // 跳过空白字符
+            }
+
+            tokens.push(Token {
+                term: word.word.to_owned(),
+                start: word.start,
+                end: word.end,
+                position,
+            });
+
+            if is_word {
+                position += 1;
+            }
+        }
+
+        tokens
+    }
+}
+
+/// 汉字按相邻两个字切分（只有一个字时单独输出），其他文字按字母和数字的连续片段切分，
+/// 空白和标点不输出；不需要词典
+pub struct CjkBigramTokenizer;
+
+impl Tokenizer for CjkBigramTokenizer {
+    fn tokenize(&self, text: &str) -> Vec<Token> {
+        let chars = text.chars().collect::<Vec<_>>();
+        let mut tokens = vec![];
+        let mut i = 0;
+        while i < chars.len() {
+            let cjk = is_cjk(chars[i]);
+            if !cjk && !chars[i].is_alphanumeric() {
+                i += 1;
+                continue;
+            }
+            let mut j = i + 1;
+            while j < chars.len()
+                && is_cjk(chars[j]) == cjk
+                && (cjk || (chars[j].is_alphanumeric() && !is_cjk(chars[j])))
+            {
+                j += 1;
+            }
+
+            let mut push = |start: usize, end: usize| {
+                tokens.push(Token {
+                    term: chars[start..end].iter().collect(),
+                    start,
+                    end,
+                    position: tokens.len(),
+                })
+            };
+            if cjk && j - i > 1 {
+                (i..j - 1).for_each(|k| push(k, k + 2));
+            } else {
+                push(i, j);
+            }
+            i = j;
+        }
+        tokens
+    }
+}
+
+/// 是否为汉字（含扩展 A 区和兼容汉字）
+fn is_cjk(c: char) -> bool {
+    matches!(c, '\u{3400}'..='\u{4dbf}' | '\u{4e00}'..='\u{9fff}' | '\u{f900}'..='\u{faff}')
+}
+
+/// 英文（只含 ASCII 字符的词）转小写
+pub struct AsciiLowercaseFilter;
+
+impl TokenFilter for AsciiLowercaseFilter {
+    fn filter(&self, mut tokens: Vec<Token>) -> Vec<Token> {
+        for token in &mut tokens {
+            if token.term.is_ascii() {
+                token.term.make_ascii_lowercase();
+            }
+        }
+        tokens
+    }
+}
+
+/// 英文（只含 ASCII 字符的词）提取词干
+pub struct EnglishStemFilter {
+    stemmer: Stemmer,
+}
+
+impl EnglishStemFilter {
+    pub fn new() -> Self {
+        Self {
+            stemmer: Stemmer::create(Algorithm::English),
+        }
+    }
+}
+
+impl Default for EnglishStemFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TokenFilter for EnglishStemFilter {
+    fn filter(&self, mut tokens: Vec<Token>) -> Vec<Token> {
+        for token in &mut tokens {
+            if token.term.is_ascii() {
+                token.term = self.stemmer.stem(&token.term).into_owned();
+            }
+        }
+        tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 去掉连字符
+    struct RemoveHyphen;
+
+    impl CharFilter for RemoveHyphen {
+        fn filter(&self, chars: Vec<(char, usize)>) -> Vec<(char, usize)> {
+            chars.into_iter().filter(|&(c, _)| c != '-').collect()
+        }
+    }
+
+    fn terms(tokens: &[Token]) -> Vec<(&str, usize, usize, usize)> {
+        tokens
+            .iter()
+            .map(|t| (t.term.as_str(), t.start, t.end, t.position))
+            .collect()
+    }
+
+    #[test]
+    fn test_analyzer() {
+        // 默认：jieba 切分，英文转小写并提取词干
+        let analyzer = AnalyzerChain::default();
+        assert_eq!(
+            terms(&analyzer.analyze("研究生 Lectures")),
+            vec![("研究", 0, 2, 0), ("研究生", 0, 3, 0), ("lectur", 4, 12, 1)]
+        );
+        assert_eq!(analyzer.normalize("Lectures").as_deref(), Some("lectur"));
+
+        // 二元切分，不提取词干
+        let analyzer = AnalyzerChain::from_config(&AnalyzerConfig {
+            tokenizer: TokenizerKind::Bigram,
+            stemming: false,
+            ..Default::default()
+        });
+        assert_eq!(
+            terms(&analyzer.analyze("研究生，Lectures 2023")),
+            vec![
+                ("研究", 0, 2, 0),
+                ("究生", 1, 3, 1),
+                ("lectures", 4, 12, 2),
+                ("2023", 13, 17, 3)
+            ]
+        );
+
+        // 字符过滤后的位置对应原文
+        let analyzer = AnalyzerChain::new(CjkBigramTokenizer).char_filter(RemoveHyphen);
+        assert_eq!(
+            terms(&analyzer.analyze("e-mail 通知")),
+            vec![("email", 0, 6, 0), ("通知", 7, 9, 1)]
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use crate::core::{max_merge, IndexStats, TermScore};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sprs::{CsVec, CsVecView};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Bm25Scorer {
+    /// 词频饱和参数
+    k1: f64,
+    /// 文档长度归一化参数
+    b: f64,
+    /// 逆文档频率, shape(nt,)
+    idf: Vec<f64>,
+    /// 平均文档长度
+    avgdl: f64,
+}
+
+impl Default for Bm25Scorer {
+    fn default() -> Self {
+        Self::new(1.2, 0.75)
+    }
+}
+
+impl Bm25Scorer {
+    pub fn new(k1: f64, b: f64) -> Self {
+        Self {
+            k1,
+            b,
+            idf: vec![],
+            avgdl: 0.0,
+        }
+    }
+
+    /// * `stats`: 所有文档（所有段）的统计量
+    pub fn fit(&mut self, stats: &IndexStats) {
+        tracing::info!("[Bm25Scorer] 计算 IDF 和平均文档长度");
+        let start_time = std::time::Instant::now();
+
+        let nd = stats.n_docs as f64;
+
+        // 计算逆文档频率 (Lucene 的形式，保证非负)
+        // idf(t) = ln(1 + (N - df(t) + 0.5) / (df(t) + 0.5))
+        self.idf = stats
+            .df
+            .iter()
+            .map(|&df| {
+                let df = df as f64;
+                (1.0 + (nd - df + 0.5) / (df + 0.5)).ln()
+            })
+            .collect();
+        self.avgdl = stats.avg_len();
+
+        tracing::info!(
+            "[Bm25Scorer] 计算完成，用时 {:?}，平均文档长度 {:.2}",
+            start_time.elapsed(),
+            self.avgdl
+        );
+    }
+
+    /// * `q`: 搜索词词频, shape(nt,)
+    /// * `x`: 文档词频, shape(nt,)
+    /// * `doc_len`: 文档长度（词数）
+    /// * `returns`: BM25 得分
+    pub fn score(&self, q: CsVecView<usize>, x: CsVecView<usize>, doc_len: f64) -> f64 {
+        // 长度归一化因子 k1 * (1 - b + b * |d| / avgdl)
+        let norm = self.k1 * (1.0 - self.b + self.b * doc_len / self.avgdl);
+
+        q.iter()
+            .filter(|&(_t, &v)| v > 0)
+            .filter_map(|(t, _)| x.get(t).map(|&f| (t, f as f64)))
+            .map(|(t, f)| self.idf[t] * f * (self.k1 + 1.0) / (f + norm))
+            .sum()
+    }
+
+    /// 每个词项对得分的最大贡献
+    /// * `rows`: 所有文档的词频, shape(nt,)，以及文档长度，并行遍历
+    /// * `nt`: 词汇量
+    pub fn max_scores(
+        &self,
+        rows: impl ParallelIterator<Item = (CsVec<usize>, f64)>,
+        nt: usize,
+    ) -> Vec<f64> {
+        rows.fold(
+            || vec![0.0; nt],
+            |mut max, (x, doc_len)| {
+                let norm = self.k1 * (1.0 - self.b + self.b * doc_len / self.avgdl);
+                for (t, &f) in x.iter() {
+                    let f = f as f64;
+                    let score = self.idf[t] * f * (self.k1 + 1.0) / (f + norm);
+                    max[t] = f64::max(max[t], score);
+                }
+                max
+            },
+        )
+        .reduce(|| vec![0.0; nt], max_merge)
+    }
+
+    /// 每个搜索词项对 BM25 得分的贡献 `idf * f * (k1 + 1) / (f + norm)`，参数同 [`Bm25Scorer::score`]
+    pub fn explain(
+        &self,
+        q: CsVecView<usize>,
+        x: CsVecView<usize>,
+        doc_len: f64,
+    ) -> Vec<TermScore> {
+        let norm = self.k1 * (1.0 - self.b + self.b * doc_len / self.avgdl);
+
+        q.iter()
+            .filter(|&(_t, &v)| v > 0)
+            .map(|(t, &v)| {
+                let tf = x.get(t).copied().unwrap_or(0);
+                let f = tf as f64;
+                let weight = f * (self.k1 + 1.0) / (f + norm);
+                TermScore {
+                    id: t,
+                    tf,
+                    idf: self.idf[t],
+                    query_weight: v as f64,
+                    weight,
+                    contribution: self.idf[t] * weight,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        almost_eq::AlmostEq,
+        core::{cs_helper, PerField},
+    };
+    use sprs::{CsMat, CsVec};
+
+    #[test]
+    fn test_bm25() {
+        let x = cs_helper::cs_mat_from_cs_vecs(&[
+            CsVec::new(3, vec![0, 1], vec![1, 1]),
+            CsVec::new(3, vec![0, 2], vec![3, 1]),
+            CsVec::new(3, vec![2], vec![2]),
+        ]);
+        let mut bm25 = Bm25Scorer::new(1.2, 0.75);
+        bm25.fit(&IndexStats::build(&PerField {
+            title: x.view(),
+            text: CsMat::zero((3, 3)).view(),
+            attachments: CsMat::zero((3, 3)).view(),
+        }));
+
+        // df(0) = 2, N = 3 => idf = ln(1 + 1.5 / 2.5)
+        let idf0 = (1.0 + 1.5 / 2.5_f64).ln();
+        // avgdl = 8 / 3
+        let avgdl = 8.0 / 3.0;
+
+        let q = CsVec::new(3, vec![0], vec![1]);
+        let s0 = bm25.score(q.view(), x.outer_view(0).unwrap(), 2.0);
+        let s1 = bm25.score(q.view(), x.outer_view(1).unwrap(), 4.0);
+        let s2 = bm25.score(q.view(), x.outer_view(2).unwrap(), 2.0);
+
+        let norm0 = 1.2 * (1.0 - 0.75 + 0.75 * 2.0 / avgdl);
+        assert!(s0.almost_eq(&(idf0 * 2.2 / (1.0 + norm0)), 1e-6));
+        // 词频越高得分越高，但会饱和
+        assert!(s1 > s0);
+        assert!(s1 < 3.0 * s0);
+        // 不包含词项的文档得分为 0
+        assert!(s2.almost_eq(&0.0, 1e-6));
+
+        // 每个词项的贡献之和等于得分
+        let q = CsVec::new(3, vec![0, 2], vec![1, 1]);
+        let x1 = x.outer_view(1).unwrap();
+        let terms = bm25.explain(q.view(), x1, 4.0);
+        assert_eq!(terms.iter().map(|t| t.tf).collect::<Vec<_>>(), vec![3, 1]);
+        assert!(terms
+            .iter()
+            .map(|t| t.contribution)
+            .sum::<f64>()
+            .almost_eq(&bm25.score(q.view(), x1, 4.0), 1e-9));
+    }
+}
@@ -0,0 +1,223 @@
+use crate::core::{max_merge, Field, IndexStats, PerField, TermScore};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sprs::CsVecView;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Bm25fScorer {
+    /// 词频饱和参数
+    k1: f64,
+    /// 每个字段的长度归一化参数
+    b: PerField<f64>,
+    /// 逆文档频率（按整篇文档计算）, shape(nt,)
+    idf: Vec<f64>,
+    /// 每个字段的平均长度
+    avg_field_len: PerField<f64>,
+}
+
+impl Bm25fScorer {
+    pub fn new(k1: f64, b: PerField<f64>) -> Self {
+        Self {
+            k1,
+            b,
+            idf: vec![],
+            avg_field_len: PerField::default(),
+        }
+    }
+
+    /// * `stats`: 所有文档（所有段）的统计量
+    pub fn fit(&mut self, stats: &IndexStats) {
+        tracing::info!("[Bm25fScorer] 计算 IDF 和平均字段长度");
+        let start_time = std::time::Instant::now();
+
+        let nd = stats.n_docs as f64;
+
+        // idf(t) = ln(1 + (N - df(t) + 0.5) / (df(t) + 0.5))
+        self.idf = stats
+            .df
+            .iter()
+            .map(|&df| {
+                let df = df as f64;
+                (1.0 + (nd - df + 0.5) / (df + 0.5)).ln()
+            })
+            .collect();
+        self.avg_field_len = stats.avg_field_len();
+
+        tracing::info!(
+            "[Bm25fScorer] 计算完成，用时 {:?}，平均字段长度 {:?}",
+            start_time.elapsed(),
+            self.avg_field_len
+        );
+    }
+
+    /// * `q`: 搜索词词频, shape(nt,)
+    /// * `x`: 文档每个字段的词频, shape(nt,)
+    /// * `field_len`: 文档每个字段的长度
+    /// * `boost`: 每个字段的权重
+    /// * `returns`: BM25F 得分
+    pub fn score(
+        &self,
+        q: CsVecView<usize>,
+        x: &PerField<CsVecView<usize>>,
+        field_len: &PerField<f64>,
+        boost: &PerField<f64>,
+    ) -> f64 {
+        let norm = self.norm(field_len);
+
+        q.iter()
+            .filter(|&(_t, &v)| v > 0)
+            .map(|(t, _)| {
+                let tf = Self::tf(t, x, &norm, boost);
+                self.idf[t] * tf * (self.k1 + 1.0) / (tf + self.k1)
+            })
+            .sum()
+    }
+
+    /// 每个搜索词项对 BM25F 得分的贡献，参数同 [`Bm25fScorer::score`]
+    pub fn explain(
+        &self,
+        q: CsVecView<usize>,
+        x: &PerField<CsVecView<usize>>,
+        field_len: &PerField<f64>,
+        boost: &PerField<f64>,
+    ) -> Vec<TermScore> {
+        let norm = self.norm(field_len);
+
+        q.iter()
+            .filter(|&(_t, &v)| v > 0)
+            .map(|(t, &v)| {
+                let tf = Self::tf(t, x, &norm, boost);
+                let weight = tf * (self.k1 + 1.0) / (tf + self.k1);
+                TermScore {
+                    id: t,
+                    tf: x.iter().filter_map(|(_, x)| x.get(t)).sum(),
+                    idf: self.idf[t],
+                    query_weight: v as f64,
+                    weight,
+                    contribution: self.idf[t] * weight,
+                }
+            })
+            .collect()
+    }
+
+    /// 每个字段中每个词项归一化词频 `tf / norm` 的最大值
+    /// * `rows`: 所有文档每个字段的词频, shape(nt,)，以及每个字段的长度，并行遍历
+    /// * `nt`: 词汇量
+    pub fn max_field_tf<'a>(
+        &self,
+        rows: impl ParallelIterator<Item = (PerField<CsVecView<'a, usize>>, PerField<f64>)>,
+        nt: usize,
+    ) -> PerField<Vec<f64>> {
+        let zeros = || PerField::from_fn(|_| vec![0.0; nt]);
+        rows.fold(zeros, |mut max, (x, field_len)| {
+            let norm = self.norm(&field_len);
+            for f in Field::ALL {
+                for (t, &v) in x[f].iter() {
+                    max[f][t] = f64::max(max[f][t], v as f64 / norm[f]);
+                }
+            }
+            max
+        })
+        .reduce(zeros, |mut a, mut b| {
+            PerField::from_fn(|f| max_merge(std::mem::take(&mut a[f]), std::mem::take(&mut b[f])))
+        })
+    }
+
+    /// 由每个字段归一化词频的最大值得到词项t对得分贡献的上界（得分随加权词频递增）
+    /// * `max_tf`: 词项t在每个字段中归一化词频的最大值，见 [`Bm25fScorer::max_field_tf`]
+    /// * `boost`: 每个字段的权重
+    pub fn upper_bound(&self, t: usize, max_tf: &PerField<f64>, boost: &PerField<f64>) -> f64 {
+        let tf = Field::ALL
+            .iter()
+            .map(|&f| boost[f].max(0.0) * max_tf[f])
+            .sum::<f64>();
+        self.idf.get(t).copied().unwrap_or(0.0) * tf * (self.k1 + 1.0) / (tf + self.k1)
+    }
+
+    /// 每个字段的长度归一化因子 1 - b + b * |d_f| / avgdl_f
+    fn norm(&self, field_len: &PerField<f64>) -> PerField<f64> {
+        PerField::from_fn(|f: Field| {
+            let avg = self.avg_field_len[f];
+            if avg > 0.0 {
+                1.0 - self.b[f] + self.b[f] * field_len[f] / avg
+            } else {
+                1.0
+            }
+        })
+    }
+
+    /// 加权合并各字段的归一化词频
+    fn tf(
+        t: usize,
+        x: &PerField<CsVecView<usize>>,
+        norm: &PerField<f64>,
+        boost: &PerField<f64>,
+    ) -> f64 {
+        Field::ALL
+            .iter()
+            .filter_map(|&f| x[f].get(t).map(|&v| boost[f] * v as f64 / norm[f]))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::cs_helper;
+    use sprs::CsVec;
+
+    #[test]
+    fn test_bm25f() {
+        // 两个文档，词项0分别出现在标题和正文中
+        let fields = PerField {
+            title: cs_helper::cs_mat_from_cs_vecs(&[
+                CsVec::new(2, vec![0], vec![1]),
+                CsVec::new(2, vec![1], vec![1]),
+            ]),
+            text: cs_helper::cs_mat_from_cs_vecs(&[
+                CsVec::new(2, vec![1], vec![1]),
+                CsVec::new(2, vec![0], vec![1]),
+            ]),
+            attachments: cs_helper::cs_mat_from_cs_vecs(&[
+                CsVec::new(2, vec![], vec![]),
+                CsVec::new(2, vec![], vec![]),
+            ]),
+        };
+        let b = PerField {
+            title: 0.5,
+            text: 0.75,
+            attachments: 0.5,
+        };
+        let mut bm25f = Bm25fScorer::new(1.2, b);
+        bm25f.fit(&IndexStats::build(&fields.map(|_, x| x.view())));
+
+        let q = CsVec::new(2, vec![0], vec![1]);
+        let rows = |d| fields.map(|_, x| x.outer_view(d).unwrap());
+        let len =
+            |d| fields.map(|_, x| x.outer_view(d).unwrap().data().iter().sum::<usize>() as f64);
+        let boost = PerField {
+            title: 3.0,
+            text: 1.0,
+            attachments: 1.0,
+        };
+
+        // 标题命中权重更高
+        let s0 = bm25f.score(q.view(), &rows(0), &len(0), &boost);
+        let s1 = bm25f.score(q.view(), &rows(1), &len(1), &boost);
+        assert!(s0 > s1);
+        let terms = bm25f.explain(q.view(), &rows(0), &len(0), &boost);
+        assert_eq!(terms.len(), 1);
+        assert_eq!(terms[0].tf, 1);
+        assert!((terms[0].contribution - s0).abs() < 1e-9);
+
+        // 字段权重相同时得分相同
+        let boost = PerField {
+            title: 1.0,
+            text: 1.0,
+            attachments: 1.0,
+        };
+        let s0 = bm25f.score(q.view(), &rows(0), &len(0), &boost);
+        let s1 = bm25f.score(q.view(), &rows(1), &len(1), &boost);
+        assert!((s0 - s1).abs() < 1e-6);
+    }
+}
@@ -1,115 +1,201 @@
-use crate::core::{cs_helper, Vocabulary};
-use jieba_rs::Jieba;
-use rust_stemmers::{Algorithm, Stemmer};
-use sprs::{CsMat, CsMatView};
+use crate::core::{cs_helper, Analyzer, AnalyzerChain, Token, Vocabulary};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sprs::{CsMat, CsVec};
+use std::collections::HashSet;
 
-pub struct CountVectorizer {
+#[derive(Deserialize, Serialize)]
+pub struct CountVectorizer<A = AnalyzerChain> {
     /// 词汇表
     vocab: Vocabulary,
-    /// 词频 shape(nd, nt)
-    data: CsMat<usize>,
-    /// 词干提取器
-    stemmer: Stemmer,
-    /// 分词器
-    jieba: Jieba,
+    /// 分析器（不保存，读取索引时重新创建）
+    #[serde(skip)]
+    analyzer: A,
+}
+
+impl<A: Analyzer + Default> Default for CountVectorizer<A> {
+    fn default() -> Self {
+        Self::with_analyzer(A::default())
+    }
 }
 
 impl CountVectorizer {
+    /// 使用默认的分析器
     pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<A: Analyzer> CountVectorizer<A> {
+    pub fn with_analyzer(analyzer: A) -> Self {
         Self {
             vocab: Vocabulary::default(),
-            data: CsMat::zero((0, 0)),
-            stemmer: Stemmer::create(Algorithm::English),
-            jieba: Jieba::new(),
+            analyzer,
         }
     }
 
+    pub fn analyzer(&self) -> &A {
+        &self.analyzer
+    }
+
+    /// 替换分析器（如读取索引后按配置重新创建），需与构建索引时的分析器一致
+    pub fn set_analyzer(&mut self, analyzer: A) {
+        self.analyzer = analyzer;
+    }
+
     pub fn tokenize(&self, x: &[String]) -> Vec<Vec<String>> {
         x.iter()
-            .map(|s| {
-                let mut tokens = Vec::new();
+            .map(|s| self.analyze(s).into_iter().map(|t| t.term).collect())
+            .collect()
+    }
 
-                for token in self.jieba.cut_for_search(s, true) {
-                    if token.chars().all(|c| c.is_whitespace()) {
-                        continue; // 跳过空白字符
-                    }
+    /// 分词，并记录每个词的位置
+    pub fn analyze(&self, s: &str) -> Vec<Token> {
+        self.analyzer.analyze(s)
+    }
 
-                    if token.chars().all(|c| c.is_ascii()) {
-                        let token = token.to_lowercase(); // 转小写
-                        tokens.push(self.stemmer.stem(&token).into_owned());
-                    } else {
-                        tokens.push(token.to_owned());
-                    }
-                }
+    /// 分词，同时返回被删除的词项（如停用词）
+    pub fn analyze_with_removed(&self, s: &str) -> (Vec<Token>, Vec<String>) {
+        self.analyzer.analyze_with_removed(s)
+    }
 
-                tokens
-            })
-            .collect()
+    /// 词转换为词项（如英文转小写并提取词干），被过滤掉时原样返回
+    pub fn normalize(&self, word: &str) -> String {
+        self.analyzer
+            .normalize(word)
+            .unwrap_or_else(|| word.to_owned())
     }
 
     /// 训练
-    pub fn fit(&mut self, x: &[String]) {
-        tracing::info!("[CountVectorizer] 开始训练 {} 个文档", x.len());
+    /// * `returns`: 所有文档词频, shape(nd,nt)
+    pub fn fit(&mut self, x: &[String]) -> CsMat<usize> {
+        let analyzed = x.par_iter().map(|s| self.analyze(s)).collect::<Vec<_>>();
+        self.fit_analyzed(&[&analyzed]).remove(0)
+    }
+
+    /// 按字段训练，词汇表由所有字段共享
+    /// * `fields`: 每个字段的所有文档的分词结果
+    /// * `returns`: 每个字段的词频矩阵, shape(nd,nt)
+    pub fn fit_analyzed(&mut self, fields: &[&[Vec<Token>]]) -> Vec<CsMat<usize>> {
+        // 清空词汇表
+        self.vocab = Vocabulary::default();
+        self.partial_fit(fields)
+    }
+
+    /// 增量训练，新词追加到词汇表末尾（已有词项的id不变）
+    /// * `fields`: 每个字段的新文档的分词结果
+    /// * `returns`: 每个字段的新文档的词频矩阵, shape(nd_new,nt)
+    pub fn partial_fit(&mut self, fields: &[&[Vec<Token>]]) -> Vec<CsMat<usize>> {
+        let nd = fields.first().map_or(0, |x| x.len());
+        tracing::info!(
+            "[CountVectorizer] 开始训练 {} 个文档，{} 个字段",
+            nd,
+            fields.len()
+        );
         let start_time = std::time::Instant::now();
 
-        // 分词
-        tracing::info!("[CountVectorizer] (1/3) 分词");
-        let tokenized = self.tokenize(x);
-
-        // 构建词汇表
-        tracing::info!("[CountVectorizer] (2/3) 构建词汇表");
-        self.vocab = Vocabulary::default(); // 清空词汇表
-        for doc in &tokenized {
-            for token in doc {
-                self.vocab.insert(token);
-            }
-        }
+        // 构建词汇表（按文档、字段的顺序插入，保证词项id稳定）：
+        // 文档分块并行找出新词（块内按出现顺序），再按块的顺序插入，结果与逐个插入相同
+        tracing::info!("[CountVectorizer] (1/2) 构建词汇表");
+        let phase_time = std::time::Instant::now();
+        let chunk_size = nd.div_ceil(rayon::current_num_threads()).max(1);
+        let new_terms = (0..nd)
+            .step_by(chunk_size)
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map(|start| {
+                let mut seen = HashSet::new();
+                let mut terms = vec![];
+                for d in start..(start + chunk_size).min(nd) {
+                    for field in fields {
+                        for token in &field[d] {
+                            let term = token.term.as_str();
+                            if self.vocab.id(term).is_none() && seen.insert(term) {
+                                terms.push(term);
+                            }
+                        }
+                    }
+                }
+                terms
+            })
+            .collect::<Vec<_>>();
+        self.vocab.extend(new_terms.into_iter().flatten());
+        tracing::info!(
+            "[CountVectorizer] (1/2) 构建词汇表完成，用时 {:?}",
+            phase_time.elapsed()
+        );
 
         // 构建词频矩阵
-        tracing::info!("[CountVectorizer] (3/3) 构建词频矩阵");
-        self.data = self.count(tokenized);
+        tracing::info!("[CountVectorizer] (2/2) 构建词频矩阵");
+        let phase_time = std::time::Instant::now();
+        let field_data = fields
+            .iter()
+            .map(|field| self.count(field))
+            .collect::<Vec<_>>();
+        tracing::info!(
+            "[CountVectorizer] (2/2) 构建词频矩阵完成，用时 {:?}",
+            phase_time.elapsed()
+        );
 
         tracing::info!(
             "[CountVectorizer] 训练完成，用时 {:?}，词汇表大小 {}",
             start_time.elapsed(),
             self.vocab.len()
         );
+
+        field_data
     }
 
-    pub fn get_data(&self) -> CsMatView<usize> {
-        self.data.view()
+    pub fn vocab(&self) -> &Vocabulary {
+        &self.vocab
     }
 
-    /// 转换
+    /// 转换（用于搜索词等少量文本，不并行）
     pub fn transform(&self, inputs: &[String]) -> CsMat<usize> {
-        let tokenized = self.tokenize(inputs);
-        self.count(tokenized)
+        if inputs.is_empty() {
+            return CsMat::zero((0, self.vocab.len()));
+        }
+        let vecs = inputs
+            .iter()
+            .map(|s| self.count_doc(&self.analyze(s)))
+            .collect::<Vec<_>>();
+        cs_helper::cs_mat_from_cs_vecs(&vecs)
     }
 
-    fn count(&self, tokenized: Vec<Vec<String>>) -> CsMat<usize> {
-        let mut vecs = vec![];
-        for doc in tokenized {
-            // 这个文档的词项id列表（会重复）
-            let mut tokens = doc
-                .into_iter()
-                .filter_map(|token| self.vocab.id(token))
-                .collect::<Vec<_>>();
+    /// 构建索引时每个文档并行计数
+    fn count(&self, analyzed: &[Vec<Token>]) -> CsMat<usize> {
+        if analyzed.is_empty() {
+            return CsMat::zero((0, self.vocab.len()));
+        }
+        let vecs = analyzed
+            .par_iter()
+            .map(|doc| self.count_doc(doc))
+            .collect::<Vec<_>>();
+        cs_helper::cs_mat_from_cs_vecs(&vecs)
+    }
 
-            // 从小到大排序
-            tokens.sort();
+    /// 一个文档的词频, shape(nt,)
+    fn count_doc(&self, doc: &[Token]) -> CsVec<usize> {
+        // 这个文档的词项id列表（会重复）
+        let mut tokens = doc
+            .iter()
+            .filter_map(|token| self.vocab.id(&token.term))
+            .collect::<Vec<_>>();
 
-            // 计算词频
-            let vec = cs_helper::cs_vec_count(self.vocab.len(), &tokens);
-            vecs.push(vec);
-        }
+        // 从小到大排序
+        tokens.sort();
 
-        cs_helper::cs_mat_from_cs_vecs(&vecs)
+        // 计算词频
+        cs_helper::cs_vec_count(self.vocab.len(), &tokens)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::{AnalyzerConfig, StopwordsConfig};
+    use jieba_rs::Jieba;
+    use rust_stemmers::{Algorithm, Stemmer};
 
     #[test]
     fn test_jieba() {
@@ -127,24 +213,92 @@ mod tests {
         assert_eq!(stemmed, "fruitless");
     }
 
+    /// 不去掉停用词和标点
+    fn without_stopwords() -> CountVectorizer {
+        CountVectorizer::with_analyzer(AnalyzerChain::from_config(&AnalyzerConfig {
+            stopwords: StopwordsConfig {
+                builtin: false,
+                punctuation: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        }))
+    }
+
     #[test]
     fn test_tokenizer() {
-        let cv = CountVectorizer::new();
+        let cv = without_stopwords();
         let s = vec!["我a b你".to_owned()];
         assert_eq!(cv.tokenize(&s), vec![vec!["我", "a", "b", "你"]]);
     }
 
+    #[test]
+    fn test_analyze() {
+        let cv = CountVectorizer::new();
+        let tokens = cv.analyze("研究生 招生");
+        let terms = tokens
+            .iter()
+            .map(|t| (t.term.as_str(), t.position))
+            .collect::<Vec<_>>();
+        // 子词“研究”与“研究生”位置相同，空格不占位置
+        assert_eq!(terms, vec![("研究", 0), ("研究生", 0), ("招生", 1)]);
+        assert_eq!((tokens[2].start, tokens[2].end), (4, 6));
+    }
+
     #[test]
     fn test_count_vectorizer() {
-        let mut cv = CountVectorizer::new();
+        let mut cv = without_stopwords();
         let x = vec!["a b c", "a b", "a"]
             .into_iter()
             .map(|s| s.to_owned())
             .collect::<Vec<_>>();
-        cv.fit(&x);
-        let data = cv.get_data();
+        let data = cv.fit(&x);
         assert_eq!(data.indptr().as_slice().unwrap(), &[0, 3, 5, 6]);
         assert_eq!(data.indices(), &[0, 1, 2, 0, 1, 0]);
         assert_eq!(data.data(), &[1, 1, 1, 1, 1, 1]);
+
+        // 增量训练，新词追加在后面
+        let new = ["d a".to_owned()];
+        let analyzed = new.iter().map(|s| cv.analyze(s)).collect::<Vec<_>>();
+        let data = cv.partial_fit(&[&analyzed]).remove(0);
+        assert_eq!(data.shape(), (1, 4));
+        assert_eq!(data.indptr().as_slice().unwrap(), &[0, 2]);
+        assert_eq!(data.indices(), &[0, 3]);
+    }
+
+    #[test]
+    fn test_parallel_fit() {
+        let x = [
+            "研究生招生考试",
+            "本科生选课 x y",
+            "研究生奖学金评选",
+            "讲座 z x",
+            "招生",
+        ]
+        .into_iter()
+        .cycle()
+        .take(50)
+        .enumerate()
+        .map(|(i, s)| format!("{} {}", s, i))
+        .collect::<Vec<_>>();
+
+        // 线程数不同时词项id和词频矩阵都相同
+        let fit = |threads| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            let mut cv = CountVectorizer::new();
+            let data = pool.install(|| cv.fit(&x));
+            let vocab = (0..cv.vocab().len())
+                .map(|t| cv.vocab().word(t).to_owned())
+                .collect::<Vec<_>>();
+            (vocab, data)
+        };
+        let (vocab, data) = fit(1);
+        assert_eq!(&vocab[..4], &["研究", "研究生", "招生", "考试"]);
+        for threads in [2, 3, 8] {
+            assert_eq!(fit(threads), (vocab.clone(), data.clone()));
+        }
     }
 }
@@ -1,11 +1,15 @@
-use sprs::{CsMat, CsVec, CsVecView};
+use sprs::{CsMat, CsMatView, CsVec, CsVecView};
 use std::cmp::Ordering;
 
 /// 余弦相似度
 /// * `v1`: 值为分数, shape(nt,)
 /// * `v2`: 值为分数, shape(nt,)
 pub fn cos_sim(v1: CsVecView<f64>, v2: CsVecView<f64>) -> f64 {
-    v1.dot(&v2) / (v1.dot(&v1).sqrt() * v2.dot(&v2).sqrt())
+    let norm = v1.dot(&v1).sqrt() * v2.dot(&v2).sqrt();
+    if norm == 0.0 {
+        return 0.0; // 零向量（如只有 NOT 的查询）
+    }
+    v1.dot(&v2) / norm
 }
 
 /// 文档列表交集
@@ -40,6 +44,67 @@ pub fn intersection(v1: CsVecView<bool>, v2: CsVecView<bool>) -> CsVec<bool> {
     CsVec::new(v1.dim(), result, vec![true; len])
 }
 
+/// 文档列表并集
+/// * `v1`: 值为t是否在d中, shape(nt,)
+/// * `v2`: 值为t是否在d中, shape(nt,)
+pub fn union(v1: CsVecView<bool>, v2: CsVecView<bool>) -> CsVec<bool> {
+    // 文档id列表
+    let mut result = vec![];
+    let mut i = 0;
+    let mut j = 0;
+
+    while i < v1.indices().len() || j < v2.indices().len() {
+        let t1 = v1.indices().get(i);
+        let t2 = v2.indices().get(j);
+
+        match (t1, t2) {
+            (Some(&t1), Some(&t2)) if t1 == t2 => {
+                result.push(t1);
+                i += 1;
+                j += 1;
+            }
+            (Some(&t1), Some(&t2)) if t1 < t2 => {
+                result.push(t1);
+                i += 1;
+            }
+            (Some(&t1), None) => {
+                result.push(t1);
+                i += 1;
+            }
+            (_, Some(&t2)) => {
+                result.push(t2);
+                j += 1;
+            }
+            (None, None) => unreachable!(),
+        }
+    }
+
+    let len = result.len();
+    CsVec::new(v1.dim(), result, vec![true; len])
+}
+
+/// 文档列表差集，在 `v1` 中但不在 `v2` 中
+/// * `v1`: 值为t是否在d中, shape(nt,)
+/// * `v2`: 值为t是否在d中, shape(nt,)
+pub fn difference(v1: CsVecView<bool>, v2: CsVecView<bool>) -> CsVec<bool> {
+    // 文档id列表
+    let mut result = vec![];
+    let mut j = 0;
+
+    for &t1 in v1.indices() {
+        // 跳过 v2 中更小的文档id
+        while j < v2.indices().len() && v2.indices()[j] < t1 {
+            j += 1;
+        }
+        if j >= v2.indices().len() || v2.indices()[j] != t1 {
+            result.push(t1);
+        }
+    }
+
+    let len = result.len();
+    CsVec::new(v1.dim(), result, vec![true; len])
+}
+
 /// 计算数量的稀疏向量
 /// * `n`: 向量长度
 /// * `elems`: 序号列表（重复、要求排好序）
@@ -80,6 +145,49 @@ pub fn cs_mat_from_cs_vecs<T: Clone>(vecs: &[CsVec<T>]) -> CsMat<T> {
     CsMat::new((rows, cols), indptr, indices, data)
 }
 
+/// 纵向拼接稀疏矩阵（CSR），列数取两者中较大的
+/// * `m1`: shape(n1, c1)
+/// * `m2`: shape(n2, c2)
+/// * `returns`: shape(n1 + n2, max(c1, c2))
+pub fn cs_mat_vstack<T: Clone>(m1: CsMatView<T>, m2: CsMatView<T>) -> CsMat<T> {
+    assert!(m1.is_csr() && m2.is_csr());
+    let rows = m1.rows() + m2.rows();
+    let cols = m1.cols().max(m2.cols());
+
+    let mut indptr = m1.indptr().to_proper().to_vec();
+    let mut indices = m1.indices().to_vec();
+    let mut data = m1.data().to_vec();
+
+    for row in m2.outer_iterator() {
+        indices.extend_from_slice(row.indices());
+        data.extend_from_slice(row.data());
+        indptr.push(indices.len());
+    }
+
+    CsMat::new((rows, cols), indptr, indices, data)
+}
+
+/// 取出稀疏矩阵（CSR）中的若干行
+/// * `m`: shape(n, c)
+/// * `rows`: 行号
+/// * `returns`: shape(rows.len(), c)
+pub fn cs_mat_select_rows<T: Clone>(m: CsMatView<T>, rows: &[usize]) -> CsMat<T> {
+    assert!(m.is_csr());
+
+    let mut indptr = vec![0];
+    let mut indices = vec![];
+    let mut data = vec![];
+
+    for &i in rows {
+        let row = m.outer_view(i).unwrap();
+        indices.extend_from_slice(row.indices());
+        data.extend_from_slice(row.data());
+        indptr.push(indices.len());
+    }
+
+    CsMat::new((rows.len(), m.cols()), indptr, indices, data)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::almost_eq::AlmostEq;
@@ -91,6 +199,9 @@ mod tests {
         let v1 = CsVec::new(3, vec![0, 1], vec![1.0, 1.0]);
         let v2 = CsVec::new(3, vec![1, 2], vec![1.0, 1.0]);
         assert!(cos_sim(v1.view(), v2.view()).almost_eq(&0.5, 1e-6));
+
+        let v3 = CsVec::new(3, vec![], vec![]);
+        assert!(cos_sim(v1.view(), v3.view()).almost_eq(&0.0, 1e-6));
     }
 
     #[test]
@@ -102,6 +213,24 @@ mod tests {
         assert_eq!(v3.data(), vec![true; 3]);
     }
 
+    #[test]
+    fn test_union() {
+        let v1 = CsVec::new(10, vec![1, 2, 5, 6, 9], vec![true; 5]);
+        let v2 = CsVec::new(10, vec![1, 3, 5, 7], vec![true; 4]);
+        let v3 = union(v1.view(), v2.view());
+        assert_eq!(v3.indices(), vec![1, 2, 3, 5, 6, 7, 9]);
+        assert_eq!(v3.data(), vec![true; 7]);
+    }
+
+    #[test]
+    fn test_difference() {
+        let v1 = CsVec::new(10, vec![1, 2, 5, 6, 9], vec![true; 5]);
+        let v2 = CsVec::new(10, vec![1, 3, 5, 7], vec![true; 4]);
+        let v3 = difference(v1.view(), v2.view());
+        assert_eq!(v3.indices(), vec![2, 6, 9]);
+        assert_eq!(v3.data(), vec![true; 3]);
+    }
+
     #[test]
     fn test_cs_vec_count() {
         let tokens = vec![0, 0, 1, 1, 1, 2, 2, 2, 2];
@@ -124,4 +253,34 @@ mod tests {
         assert_eq!(mat.indices(), &[0, 1, 1, 2]);
         assert_eq!(mat.data(), &[1, 2, 3, 4]);
     }
+
+    #[test]
+    fn test_cs_mat_vstack() {
+        let m1 = cs_mat_from_cs_vecs(&[CsVec::new(2, vec![0, 1], vec![1, 2])]);
+        let m2 = cs_mat_from_cs_vecs(&[
+            CsVec::new(3, vec![2], vec![3]),
+            CsVec::new(3, vec![0], vec![4]),
+        ]);
+        let mat = cs_mat_vstack(m1.view(), m2.view());
+
+        assert_eq!(mat.shape(), (3, 3));
+        assert_eq!(mat.indptr().as_slice().unwrap(), &[0, 2, 3, 4]);
+        assert_eq!(mat.indices(), &[0, 1, 2, 0]);
+        assert_eq!(mat.data(), &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_cs_mat_select_rows() {
+        let m = cs_mat_from_cs_vecs(&[
+            CsVec::new(3, vec![0, 1], vec![1, 2]),
+            CsVec::new(3, vec![1, 2], vec![3, 4]),
+            CsVec::new(3, vec![2], vec![5]),
+        ]);
+        let mat = cs_mat_select_rows(m.view(), &[0, 2]);
+
+        assert_eq!(mat.shape(), (2, 3));
+        assert_eq!(mat.indptr().as_slice().unwrap(), &[0, 2, 3]);
+        assert_eq!(mat.indices(), &[0, 1, 2]);
+        assert_eq!(mat.data(), &[1, 2, 5]);
+    }
 }
@@ -0,0 +1,155 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 时间衰减函数
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DecayFunction {
+    /// 不衰减
+    #[default]
+    None,
+    /// 指数衰减 0.5^(age/half_life)，发布后立即开始下降
+    Exp,
+    /// 高斯衰减 0.5^((age/half_life)^2)，半衰期内下降较慢，之后下降较快
+    Gauss,
+}
+
+/// 按发布时间衰减得分
+///
+/// 最终得分为 `score * (1 - weight + weight * decay(age))`，
+/// 没有发布时间的文档视为无限久远（`decay` 为 0），发布时间晚于当前时间的按刚发布计算
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Decay {
+    pub function: DecayFunction,
+    /// 半衰期（天），衰减函数的值降到 0.5 时的文档年龄
+    pub half_life_days: f64,
+    /// 衰减所占的比例，范围 [0, 1]，为 1 时得分直接乘以衰减函数的值
+    pub weight: f64,
+}
+
+impl Default for Decay {
+    fn default() -> Self {
+        Self {
+            function: DecayFunction::None,
+            half_life_days: 180.0,
+            weight: 0.5,
+        }
+    }
+}
+
+/// 时间衰减的计算过程
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct DecayExplanation {
+    pub function: DecayFunction,
+    pub half_life_days: f64,
+    pub weight: f64,
+    /// 文档年龄（天），没有发布时间时为 `None`
+    pub age_days: Option<f64>,
+    /// 衰减函数的值, 范围 [0, 1]
+    pub decay: f64,
+    /// 文本得分乘以的系数, 范围 [1-weight, 1]
+    pub factor: f64,
+}
+
+impl Decay {
+    /// 是否需要计算衰减
+    pub fn is_enabled(&self) -> bool {
+        self.function != DecayFunction::None && self.weight > 0.0
+    }
+
+    /// 衰减函数的值, 范围 [0, 1]
+    /// * `age_days`: 文档年龄（天），`None` 表示没有发布时间
+    pub fn decay(&self, age_days: Option<f64>) -> f64 {
+        let Some(age) = age_days else {
+            return 0.0;
+        };
+        let x = age.max(0.0) / self.half_life_days.max(f64::MIN_POSITIVE);
+        match self.function {
+            DecayFunction::None => 1.0,
+            DecayFunction::Exp => 0.5f64.powf(x),
+            DecayFunction::Gauss => 0.5f64.powf(x * x),
+        }
+    }
+
+    /// 计算发布时间为 `time` 的文档的衰减
+    /// * `now`: 计算年龄的基准时间
+    pub fn explain(&self, time: Option<DateTime<Utc>>, now: DateTime<Utc>) -> DecayExplanation {
+        let age_days = time.map(|t| (now - t).num_seconds().max(0) as f64 / 86400.0);
+        let weight = self.weight.clamp(0.0, 1.0);
+        let decay = self.decay(age_days);
+        DecayExplanation {
+            function: self.function,
+            half_life_days: self.half_life_days,
+            weight,
+            age_days,
+            decay,
+            factor: 1.0 - weight + weight * decay,
+        }
+    }
+
+    /// 文本得分乘以的系数
+    pub fn factor(&self, time: Option<DateTime<Utc>>, now: DateTime<Utc>) -> f64 {
+        if !self.is_enabled() {
+            return 1.0;
+        }
+        self.explain(time, now).factor
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::almost_eq::AlmostEq;
+    use chrono::Duration;
+
+    #[test]
+    fn test_decay() {
+        let now = Utc::now();
+        let exp = Decay {
+            function: DecayFunction::Exp,
+            half_life_days: 30.0,
+            weight: 1.0,
+        };
+        assert!(exp.factor(Some(now), now).almost_eq(&1.0, 1e-6));
+        assert!(exp
+            .factor(Some(now - Duration::days(30)), now)
+            .almost_eq(&0.5, 1e-6));
+        assert!(exp
+            .factor(Some(now - Duration::days(60)), now)
+            .almost_eq(&0.25, 1e-6));
+        // 未来的时间按刚发布计算，没有时间的视为无限久远
+        assert!(exp
+            .factor(Some(now + Duration::days(5)), now)
+            .almost_eq(&1.0, 1e-6));
+        assert!(exp.factor(None, now).almost_eq(&0.0, 1e-6));
+
+        // 高斯衰减在半衰期处同样为 0.5，之前下降较慢，之后下降较快
+        let gauss = Decay {
+            function: DecayFunction::Gauss,
+            ..exp
+        };
+        assert!(gauss
+            .factor(Some(now - Duration::days(30)), now)
+            .almost_eq(&0.5, 1e-6));
+        assert!(
+            gauss.factor(Some(now - Duration::days(15)), now)
+                > exp.factor(Some(now - Duration::days(15)), now)
+        );
+        assert!(
+            gauss.factor(Some(now - Duration::days(60)), now)
+                < exp.factor(Some(now - Duration::days(60)), now)
+        );
+
+        // weight 控制衰减所占的比例
+        let half = Decay { weight: 0.5, ..exp };
+        let explanation = half.explain(Some(now - Duration::days(30)), now);
+        assert!(explanation.age_days.unwrap().almost_eq(&30.0, 1e-6));
+        assert!(explanation.decay.almost_eq(&0.5, 1e-6));
+        assert!(explanation.factor.almost_eq(&0.75, 1e-6));
+        assert!(half.factor(None, now).almost_eq(&0.5, 1e-6));
+
+        // 不衰减
+        assert!(Decay::default().factor(None, now).almost_eq(&1.0, 1e-6));
+    }
+}
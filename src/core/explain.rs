@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// 一个搜索词项对文本得分的贡献
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TermScore {
+    /// 词项id
+    pub id: usize,
+    /// 词项在文档中的出现次数（所有字段之和）
+    pub tf: usize,
+    pub idf: f64,
+    /// 词项在查询中的权重：TF-IDF 为查询向量中的值，BM25 / BM25F 为查询中的出现次数
+    pub query_weight: f64,
+    /// 词项在文档中的权重：TF-IDF 为文档向量中的 tf*idf，BM25 / BM25F 为饱和后的词频
+    pub weight: f64,
+    /// 对文本得分的贡献，所有词项之和等于文本得分
+    pub contribution: f64,
+}
@@ -0,0 +1,138 @@
+use crate::dataset::{self, Doc};
+use chrono::Datelike;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 分面统计的维度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FacetField {
+    /// 类别
+    Infotype,
+    /// 发布单位
+    User,
+    /// 发布年份
+    Year,
+}
+
+impl FacetField {
+    pub const ALL: [FacetField; 3] = [FacetField::Infotype, FacetField::User, FacetField::Year];
+
+    /// 文档在这个维度上的值
+    pub fn value(self, doc: &Doc) -> Option<String> {
+        match self {
+            FacetField::Infotype => Some(doc.infotype.clone()),
+            FacetField::User => Some(doc.user.clone()),
+            FacetField::Year => doc_year(doc).map(|y| y.to_string()),
+        }
+    }
+}
+
+/// 文档的发布年份（UTC+8）
+pub fn doc_year(doc: &Doc) -> Option<i32> {
+    Some(doc.time?.with_timezone(&dataset::timezone()).year())
+}
+
+/// 某个值的命中数
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FacetCount {
+    pub value: String,
+    pub count: usize,
+}
+
+/// 每个维度的命中数，类别和单位按数量递减排列，年份按年份递减排列
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Facets {
+    pub infotype: Vec<FacetCount>,
+    pub user: Vec<FacetCount>,
+    pub year: Vec<FacetCount>,
+}
+
+impl Facets {
+    /// 统计命中的文档中每个值的数量
+    ///
+    /// 统计某个维度时忽略这个维度自身的过滤条件，这样已选择某个单位时仍能看到其他单位的数量
+    /// * `docs`: 命中的所有文档（过滤、分页之前）
+    /// * `accept`: 文档是否满足过滤条件，第二个参数为要忽略的维度
+    pub fn collect<'a>(
+        docs: impl IntoIterator<Item = &'a Doc>,
+        accept: impl Fn(&Doc, FacetField) -> bool,
+    ) -> Facets {
+        let mut counts = FacetField::ALL.map(|_| HashMap::<String, usize>::new());
+        for doc in docs {
+            for (i, field) in FacetField::ALL.into_iter().enumerate() {
+                if !accept(doc, field) {
+                    continue;
+                }
+                if let Some(value) = field.value(doc) {
+                    *counts[i].entry(value).or_default() += 1;
+                }
+            }
+        }
+
+        let [infotype, user, year] = counts.map(|counts| {
+            counts
+                .into_iter()
+                .map(|(value, count)| FacetCount { value, count })
+                .collect::<Vec<_>>()
+        });
+        let by_count = |mut v: Vec<FacetCount>| {
+            v.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+            v
+        };
+        let mut year = year;
+        year.sort_by(|a, b| b.value.cmp(&a.value));
+
+        Facets {
+            infotype: by_count(infotype),
+            user: by_count(user),
+            year,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(value: &str, count: usize) -> FacetCount {
+        FacetCount {
+            value: value.to_owned(),
+            count,
+        }
+    }
+
+    #[test]
+    fn test_facets() {
+        let docs = [
+            Doc::builder()
+                .with_infotype("教务")
+                .with_user("教务部")
+                .with_time("2023-10-01T08:00:00.000Z"),
+            Doc::builder()
+                .with_infotype("教务")
+                .with_user("研究生院")
+                .with_time("2022-12-31T20:00:00.000Z"),
+            Doc::builder()
+                .with_infotype("讲座")
+                .with_user("教务部")
+                .with_time("2023-05-01T08:00:00.000Z"),
+            Doc::builder()
+                .with_infotype("教务")
+                .with_user("教务部")
+                .with_time("invalid"),
+        ];
+
+        let facets = Facets::collect(&docs, |_, _| true);
+        assert_eq!(facets.infotype, vec![count("教务", 3), count("讲座", 1)]);
+        assert_eq!(facets.user, vec![count("教务部", 3), count("研究生院", 1)]);
+        // 按 UTC+8 划分年份
+        assert_eq!(facets.year, vec![count("2023", 3)]);
+
+        // 只选择了单位“教务部”：单位的数量不受影响，其他维度只统计教务部的文档
+        let facets = Facets::collect(&docs, |doc, ignore| {
+            ignore == FacetField::User || doc.user == "教务部"
+        });
+        assert_eq!(facets.user, vec![count("教务部", 3), count("研究生院", 1)]);
+        assert_eq!(facets.infotype, vec![count("教务", 2), count("讲座", 1)]);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{
+    core::{InvertedIndex, PositionalIndex},
+    dataset::Doc,
+};
+use serde::{Deserialize, Serialize};
+use sprs::CsMat;
+use std::ops::{Index, IndexMut};
+
+/// 文档字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Field {
+    /// 标题
+    Title,
+    /// 正文
+    Text,
+    /// 附件名
+    Attachments,
+}
+
+impl Field {
+    pub const ALL: [Field; 3] = [Field::Title, Field::Text, Field::Attachments];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Field::Title => "title",
+            Field::Text => "text",
+            Field::Attachments => "attachments",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Field> {
+        Field::ALL.into_iter().find(|f| f.name() == name)
+    }
+
+    /// 取出文档中这个字段的文本
+    pub fn extract(self, doc: &Doc) -> String {
+        match self {
+            Field::Title => doc.title.clone(),
+            Field::Text => doc.text.clone(),
+            Field::Attachments => doc
+                .attachments
+                .iter()
+                .map(|a| a.name.clone())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+/// 单个字段的索引
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct FieldIndex {
+    /// 词频, shape(nd,nt)
+    pub data: CsMat<usize>,
+    /// 倒排索引
+    pub index: InvertedIndex,
+    /// 位置索引
+    pub positions: PositionalIndex,
+}
+
+impl Default for FieldIndex {
+    fn default() -> Self {
+        Self {
+            data: CsMat::zero((0, 0)),
+            index: InvertedIndex::default(),
+            positions: PositionalIndex::default(),
+        }
+    }
+}
+
+/// 每个字段一份的数据
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub struct PerField<T> {
+    pub title: T,
+    pub text: T,
+    pub attachments: T,
+}
+
+impl<T> PerField<T> {
+    pub fn from_fn(mut f: impl FnMut(Field) -> T) -> Self {
+        Self {
+            title: f(Field::Title),
+            text: f(Field::Text),
+            attachments: f(Field::Attachments),
+        }
+    }
+
+    pub fn map<'a, U>(&'a self, mut f: impl FnMut(Field, &'a T) -> U) -> PerField<U> {
+        PerField::from_fn(|field| f(field, &self[field]))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Field, &T)> {
+        Field::ALL
+            .into_iter()
+            .map(move |field| (field, &self[field]))
+    }
+}
+
+impl<T> Index<Field> for PerField<T> {
+    type Output = T;
+
+    fn index(&self, field: Field) -> &T {
+        match field {
+            Field::Title => &self.title,
+            Field::Text => &self.text,
+            Field::Attachments => &self.attachments,
+        }
+    }
+}
+
+impl<T> IndexMut<Field> for PerField<T> {
+    fn index_mut(&mut self, field: Field) -> &mut T {
+        match field {
+            Field::Title => &mut self.title,
+            Field::Text => &mut self.text,
+            Field::Attachments => &mut self.attachments,
+        }
+    }
+}
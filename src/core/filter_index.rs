@@ -0,0 +1,209 @@
+use crate::{
+    core::{compact_ids, cs_helper, FacetField},
+    dataset::Doc,
+};
+use serde::{Deserialize, Serialize};
+use sprs::CsVec;
+use std::collections::HashMap;
+
+/// 元数据的倒排表：每个类别、单位、发布年份（UTC+8）的文档（全局id，含已删除的文档），
+/// 用于在计算得分之前按过滤条件缩小候选文档
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct FilterIndex {
+    /// 文档总数（含已删除的文档）
+    nd: usize,
+    /// 类别 -> 文档（递增）
+    infotype: HashMap<String, Vec<usize>>,
+    /// 发布单位 -> 文档（递增）
+    user: HashMap<String, Vec<usize>>,
+    /// 发布年份 -> 文档（递增），没有发布时间的文档不在其中
+    year: HashMap<String, Vec<usize>>,
+}
+
+impl FilterIndex {
+    /// 添加新文档，id 从 `base` 开始依次分配（大于已有的文档id）
+    pub fn add(&mut self, base: usize, docs: &[Doc]) {
+        for (d, doc) in (base..).zip(docs) {
+            for field in FacetField::ALL {
+                if let Some(value) = field.value(doc) {
+                    self.values_mut(field).entry(value).or_default().push(d);
+                }
+            }
+        }
+        self.nd = base + docs.len();
+    }
+
+    /// 去掉合并段时清除的文档，其后的文档id前移
+    /// * `purged`: 清除的文档（递增）
+    pub fn compact(&mut self, purged: &[usize]) {
+        for field in FacetField::ALL {
+            let values = self.values_mut(field);
+            for docs in values.values_mut() {
+                compact_ids(docs, purged);
+            }
+            values.retain(|_, docs| !docs.is_empty());
+        }
+        self.nd -= purged.len();
+    }
+
+    fn values(&self, field: FacetField) -> &HashMap<String, Vec<usize>> {
+        match field {
+            FacetField::Infotype => &self.infotype,
+            FacetField::User => &self.user,
+            FacetField::Year => &self.year,
+        }
+    }
+
+    fn values_mut(&mut self, field: FacetField) -> &mut HashMap<String, Vec<usize>> {
+        match field {
+            FacetField::Infotype => &mut self.infotype,
+            FacetField::User => &mut self.user,
+            FacetField::Year => &mut self.year,
+        }
+    }
+
+    /// 这个维度上的值为任意一个 `values` 的文档
+    /// * `returns`: shape(nd,)
+    pub fn docs(&self, field: FacetField, values: &[String]) -> CsVec<bool> {
+        let index = self.values(field);
+        let mut docs = values
+            .iter()
+            .filter_map(|value| index.get(value))
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+        // 一个文档在每个维度上只有一个值，多个值的文档列表没有重复
+        docs.sort_unstable();
+        let len = docs.len();
+        CsVec::new(self.nd, docs, vec![true; len])
+    }
+
+    /// 按包含和排除条件过滤
+    /// * `include`: 每个维度包含的值，满足任意一个即可，没有值表示不限
+    /// * `exclude`: 每个维度排除的值，满足任意一个即排除
+    pub fn filter(
+        &self,
+        include: &[(FacetField, &[String])],
+        exclude: &[(FacetField, &[String])],
+    ) -> DocFilter {
+        let include = include
+            .iter()
+            .filter(|(_, values)| !values.is_empty())
+            .map(|&(field, values)| self.docs(field, values))
+            .reduce(|acc, v| cs_helper::intersection(acc.view(), v.view()));
+        let exclude = exclude
+            .iter()
+            .filter(|(_, values)| !values.is_empty())
+            .map(|&(field, values)| self.docs(field, values))
+            .reduce(|acc, v| cs_helper::union(acc.view(), v.view()));
+        DocFilter { include, exclude }
+    }
+}
+
+/// 按元数据过滤的文档集合：满足所有包含条件，且不满足任何排除条件
+#[derive(Debug, Default)]
+pub struct DocFilter {
+    /// 满足包含条件的文档，`None` 表示不限, shape(nd,)
+    include: Option<CsVec<bool>>,
+    /// 排除的文档，`None` 表示不排除, shape(nd,)
+    exclude: Option<CsVec<bool>>,
+}
+
+impl DocFilter {
+    /// 是否没有任何条件
+    pub fn is_empty(&self) -> bool {
+        self.include.is_none() && self.exclude.is_none()
+    }
+
+    /// 文档d是否满足条件
+    pub fn contains(&self, d: usize) -> bool {
+        let member = |docs: &CsVec<bool>| docs.indices().binary_search(&d).is_ok();
+        self.include.as_ref().is_none_or(member) && !self.exclude.as_ref().is_some_and(member)
+    }
+
+    /// 只保留满足条件的文档（合并有序的文档列表）
+    /// * `docs`: shape(nd,)
+    /// * `returns`: shape(nd,)
+    pub fn apply(&self, docs: CsVec<bool>) -> CsVec<bool> {
+        let docs = match &self.include {
+            Some(include) => cs_helper::intersection(docs.view(), include.view()),
+            None => docs,
+        };
+        match &self.exclude {
+            Some(exclude) => cs_helper::difference(docs.view(), exclude.view()),
+            None => docs,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_index() {
+        let mut index = FilterIndex::default();
+        index.add(
+            0,
+            &[
+                Doc::builder()
+                    .with_infotype("教务")
+                    .with_user("教务部")
+                    .with_time("2023-09-01T00:00:00+08:00"),
+                Doc::builder()
+                    .with_infotype("学术")
+                    .with_user("研究生院")
+                    .with_time("2022-12-31T20:00:00Z"),
+            ],
+        );
+        index.add(
+            2,
+            &[
+                Doc::builder()
+                    .with_infotype("教务")
+                    .with_user("研究生院")
+                    .with_time("2024-01-01T00:00:00+08:00"),
+                Doc::builder()
+                    .with_infotype("行政")
+                    .with_user("校办")
+                    .with_time("invalid"),
+            ],
+        );
+        let values = |v: &[&str]| v.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let docs = |f: DocFilter| f.apply(CsVec::new(4, (0..4).collect(), vec![true; 4]));
+
+        assert_eq!(
+            index
+                .docs(FacetField::Infotype, &values(&["教务"]))
+                .indices(),
+            &[0, 2]
+        );
+        // 年份按 UTC+8
+        assert_eq!(
+            index.docs(FacetField::Year, &values(&["2023"])).indices(),
+            &[0, 1]
+        );
+
+        // 同一维度的多个值满足任意一个即可，不同维度都要满足
+        let (infotype, user) = (values(&["教务", "学术"]), values(&["研究生院"]));
+        let filter = index.filter(
+            &[(FacetField::Infotype, &infotype), (FacetField::User, &user)],
+            &[],
+        );
+        assert_eq!(docs(filter).indices(), &[1, 2]);
+
+        // 排除
+        let year = values(&["2024"]);
+        let filter = index.filter(
+            &[(FacetField::Infotype, &infotype)],
+            &[(FacetField::Year, &year)],
+        );
+        assert!(filter.contains(0) && !filter.contains(2) && !filter.contains(3));
+        assert_eq!(docs(filter).indices(), &[0, 1]);
+
+        // 只有排除条件，没有发布时间的文档不被排除
+        let filter = index.filter(&[], &[(FacetField::Year, &year)]);
+        assert_eq!(docs(filter).indices(), &[0, 1, 3]);
+        assert!(index.filter(&[], &[]).is_empty());
+    }
+}
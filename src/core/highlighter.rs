@@ -0,0 +1,250 @@
+use crate::{
+    core::{CountVectorizer, Field, PerField},
+    dataset::Doc,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, ops::Range};
+
+/// 高亮参数
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct HighlightOptions {
+    /// 匹配词之前插入的标签
+    pub pre_tag: String,
+    /// 匹配词之后插入的标签
+    pub post_tag: String,
+    /// 正文片段的长度（字符）
+    pub fragment_size: usize,
+    /// 最多返回的正文片段数
+    pub number_of_fragments: usize,
+}
+
+impl Default for HighlightOptions {
+    fn default() -> Self {
+        Self {
+            pre_tag: "<em>".to_owned(),
+            post_tag: "</em>".to_owned(),
+            fragment_size: 100,
+            number_of_fragments: 3,
+        }
+    }
+}
+
+/// 高亮搜索词，并从正文中选出最匹配的片段
+///
+/// 文本与索引使用相同的分词（[`CountVectorizer::analyze`]），英文按词干、中文按切分出的词匹配；
+/// 输出的文本已做 HTML 转义，只有插入的标签是 HTML
+pub struct Highlighter<'a> {
+    count_vectorizer: &'a CountVectorizer,
+    /// 搜索词切分出的词项
+    terms: HashSet<String>,
+    options: &'a HighlightOptions,
+}
+
+impl<'a> Highlighter<'a> {
+    /// * `query`: 用于匹配的搜索词（不含排除的词）
+    pub fn new(
+        count_vectorizer: &'a CountVectorizer,
+        query: &str,
+        options: &'a HighlightOptions,
+    ) -> Self {
+        let terms = count_vectorizer
+            .analyze(query)
+            .into_iter()
+            .map(|t| t.term)
+            .collect();
+        Self {
+            count_vectorizer,
+            terms,
+            options,
+        }
+    }
+
+    /// 文档每个字段的高亮结果：完整的标题、正文片段、包含匹配的附件名
+    pub fn highlight_doc(&self, doc: &Doc) -> PerField<Vec<String>> {
+        PerField::from_fn(|field| match field {
+            Field::Title => vec![self.highlight(&doc.title)],
+            Field::Text => self.snippets(&doc.text),
+            Field::Attachments => doc
+                .attachments
+                .iter()
+                .filter(|a| !self.matches(&a.name).is_empty())
+                .map(|a| self.highlight(&a.name))
+                .collect(),
+        })
+    }
+
+    /// 高亮整段文本
+    pub fn highlight(&self, text: &str) -> String {
+        let chars = text.chars().collect::<Vec<_>>();
+        self.render(&chars, 0..chars.len(), &self.matches(text))
+    }
+
+    /// 选出匹配最多的若干片段并高亮（按在原文中的顺序），没有匹配时返回开头的一段
+    pub fn snippets(&self, text: &str) -> Vec<String> {
+        let chars = text.chars().collect::<Vec<_>>();
+        let len = chars.len();
+        let size = self.options.fragment_size.max(1);
+        let matches = self.matches(text);
+        if len == 0 || self.options.number_of_fragments == 0 {
+            return vec![];
+        }
+        if matches.is_empty() {
+            return vec![self.render(&chars, 0..size.min(len), &[])];
+        }
+
+        // 以每个匹配为锚点的窗口（匹配前留出 1/4 的上下文），
+        // 按包含的不同匹配词数、匹配次数排序
+        let mut windows = matches
+            .iter()
+            .map(|m| {
+                let start = m
+                    .start
+                    .saturating_sub(size / 4)
+                    .min(len.saturating_sub(size));
+                let window = start..(start + size).min(len);
+                let inside = matches
+                    .iter()
+                    .filter(|m| m.start >= window.start && m.end <= window.end)
+                    .collect::<Vec<_>>();
+                let distinct = inside
+                    .iter()
+                    .map(|m| {
+                        chars[m.start..m.end]
+                            .iter()
+                            .collect::<String>()
+                            .to_lowercase()
+                    })
+                    .collect::<HashSet<_>>()
+                    .len();
+                (window, distinct, inside.len())
+            })
+            .collect::<Vec<_>>();
+        windows.sort_by(|a, b| (b.1, b.2).cmp(&(a.1, a.2)).then(a.0.start.cmp(&b.0.start)));
+
+        // 选出互不重叠的窗口
+        let mut selected: Vec<Range<usize>> = vec![];
+        for (window, _, _) in windows {
+            if selected.len() >= self.options.number_of_fragments {
+                break;
+            }
+            if selected
+                .iter()
+                .all(|s| window.end <= s.start || window.start >= s.end)
+            {
+                selected.push(window);
+            }
+        }
+        selected.sort_by_key(|w| w.start);
+
+        selected
+            .into_iter()
+            .map(|window| {
+                // 不截断匹配词
+                let start = matches
+                    .iter()
+                    .find(|m| m.start < window.start && m.end > window.start)
+                    .map_or(window.start, |m| m.start);
+                let end = matches
+                    .iter()
+                    .find(|m| m.start < window.end && m.end > window.end)
+                    .map_or(window.end, |m| m.end);
+                self.render(&chars, start..end, &matches)
+            })
+            .collect()
+    }
+
+    /// 文本中与搜索词匹配的范围（字符），按起点排序，重叠的已合并
+    fn matches(&self, text: &str) -> Vec<Range<usize>> {
+        let mut ranges = self
+            .count_vectorizer
+            .analyze(text)
+            .into_iter()
+            .filter(|t| self.terms.contains(&t.term))
+            .map(|t| t.start..t.end)
+            .collect::<Vec<_>>();
+        ranges.sort_by_key(|r| (r.start, r.end));
+
+        // 合并重叠的范围（如“研究”和“研究生”）
+        let mut merged: Vec<Range<usize>> = vec![];
+        for r in ranges {
+            match merged.last_mut() {
+                Some(last) if r.start < last.end => last.end = last.end.max(r.end),
+                _ => merged.push(r),
+            }
+        }
+        merged
+    }
+
+    /// 转义 `range` 内的文本，并在匹配处插入标签
+    fn render(&self, chars: &[char], range: Range<usize>, matches: &[Range<usize>]) -> String {
+        let mut s = String::new();
+        let mut i = range.start;
+        for m in matches
+            .iter()
+            .filter(|m| m.start >= range.start && m.end <= range.end)
+        {
+            escape_into(&mut s, &chars[i..m.start]);
+            s.push_str(&self.options.pre_tag);
+            escape_into(&mut s, &chars[m.start..m.end]);
+            s.push_str(&self.options.post_tag);
+            i = m.end;
+        }
+        escape_into(&mut s, &chars[i..range.end]);
+        s.trim().to_owned()
+    }
+}
+
+/// HTML 转义
+fn escape_into(s: &mut String, chars: &[char]) {
+    for &c in chars {
+        match c {
+            '&' => s.push_str("&amp;"),
+            '<' => s.push_str("&lt;"),
+            '>' => s.push_str("&gt;"),
+            '"' => s.push_str("&quot;"),
+            '\'' => s.push_str("&#39;"),
+            c => s.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlighter() {
+        let cv = CountVectorizer::new();
+        let options = HighlightOptions::default();
+
+        // 中文按切分的词匹配，重叠的子词合并
+        let h = Highlighter::new(&cv, "研究生 招生", &options);
+        assert_eq!(
+            h.highlight("2023年研究生招生<简章>"),
+            "2023年<em>研究生</em><em>招生</em>&lt;简章&gt;"
+        );
+
+        // 英文按词干匹配
+        let h = Highlighter::new(&cv, "lecture", &options);
+        assert_eq!(
+            h.highlight("Two Lectures on AI"),
+            "Two <em>Lectures</em> on AI"
+        );
+
+        // 选出匹配最多的片段，标签可配置
+        let options = HighlightOptions {
+            pre_tag: "[".to_owned(),
+            post_tag: "]".to_owned(),
+            fragment_size: 10,
+            number_of_fragments: 1,
+        };
+        let h = Highlighter::new(&cv, "选课 通知", &options);
+        let text = "本学期选课将于下周开始，选课通知如下。其他事宜另行安排。";
+        assert_eq!(h.snippets(text), vec!["始，[选课][通知]如下。其"]);
+
+        // 没有匹配时返回开头
+        let h = Highlighter::new(&cv, "讲座", &options);
+        assert_eq!(h.snippets(text), vec!["本学期选课将于下周开"]);
+    }
+}
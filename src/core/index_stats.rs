@@ -0,0 +1,152 @@
+use crate::core::{Field, PerField};
+use serde::{Deserialize, Serialize};
+use sprs::{CsMatView, CsVecView};
+
+/// 文档集合的统计量，可以逐段累加，用于计算 IDF 和平均长度
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+pub struct IndexStats {
+    /// 文档数
+    pub n_docs: usize,
+    /// 包含词项t的文档数（任意字段出现即算）, shape(nt,)
+    pub df: Vec<usize>,
+    /// 每个字段的总长度（词数）
+    pub field_len: PerField<usize>,
+    /// 停用词过滤去掉的倒排记录数（每个文档中被去掉的不同词项数）
+    pub removed_postings: usize,
+}
+
+impl IndexStats {
+    /// * `fields`: 每个字段的所有文档词频, shape(nd,nt)
+    pub fn build(fields: &PerField<CsMatView<usize>>) -> IndexStats {
+        let nd = fields.title.rows();
+        let nt = fields.iter().map(|(_, x)| x.cols()).max().unwrap_or(0);
+
+        let mut stats = IndexStats {
+            df: vec![0; nt],
+            ..Default::default()
+        };
+        for d in 0..nd {
+            let rows = fields.map(|_, x| x.outer_view(d).unwrap());
+            stats.add_doc(&rows);
+        }
+        stats
+    }
+
+    /// 累加另一部分文档的统计量
+    pub fn add(&mut self, other: &IndexStats) {
+        self.n_docs += other.n_docs;
+        self.removed_postings += other.removed_postings;
+        if self.df.len() < other.df.len() {
+            self.df.resize(other.df.len(), 0);
+        }
+        for (t, &df) in other.df.iter().enumerate() {
+            self.df[t] += df;
+        }
+        for field in Field::ALL {
+            self.field_len[field] += other.field_len[field];
+        }
+    }
+
+    /// * `x`: 文档每个字段的词频, shape(nt,)
+    pub fn add_doc(&mut self, x: &PerField<CsVecView<usize>>) {
+        self.n_docs += 1;
+        for t in terms(x) {
+            if self.df.len() <= t {
+                self.df.resize(t + 1, 0);
+            }
+            self.df[t] += 1;
+        }
+        for field in Field::ALL {
+            self.field_len[field] += x[field].data().iter().sum::<usize>();
+        }
+    }
+
+    /// 扣除一个已删除文档
+    /// * `x`: 文档每个字段的词频, shape(nt,)
+    pub fn remove_doc(&mut self, x: &PerField<CsVecView<usize>>) {
+        self.n_docs -= 1;
+        for t in terms(x) {
+            self.df[t] -= 1;
+        }
+        for field in Field::ALL {
+            self.field_len[field] -= x[field].data().iter().sum::<usize>();
+        }
+    }
+
+    /// 词项t的文档频率
+    pub fn df(&self, t: usize) -> usize {
+        self.df.get(t).copied().unwrap_or(0)
+    }
+
+    /// 平均文档长度（所有字段）
+    pub fn avg_len(&self) -> f64 {
+        if self.n_docs == 0 {
+            return 0.0;
+        }
+        self.field_len.iter().map(|(_, &len)| len).sum::<usize>() as f64 / self.n_docs as f64
+    }
+
+    /// 每个字段的平均长度
+    pub fn avg_field_len(&self) -> PerField<f64> {
+        self.field_len.map(|_, &len| {
+            if self.n_docs > 0 {
+                len as f64 / self.n_docs as f64
+            } else {
+                0.0
+            }
+        })
+    }
+}
+
+/// 文档中出现的词项（去重）
+fn terms(x: &PerField<CsVecView<usize>>) -> Vec<usize> {
+    let mut terms = x
+        .iter()
+        .flat_map(|(_, v)| v.iter().filter(|&(_t, &v)| v > 0).map(|(t, _)| t))
+        .collect::<Vec<_>>();
+    terms.sort();
+    terms.dedup();
+    terms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::cs_helper;
+    use sprs::CsVec;
+
+    #[test]
+    fn test_index_stats() {
+        let fields = PerField {
+            title: cs_helper::cs_mat_from_cs_vecs(&[
+                CsVec::new(3, vec![0], vec![1]),
+                CsVec::new(3, vec![1], vec![1]),
+            ]),
+            text: cs_helper::cs_mat_from_cs_vecs(&[
+                CsVec::new(3, vec![0, 2], vec![2, 1]),
+                CsVec::new(3, vec![], vec![]),
+            ]),
+            attachments: cs_helper::cs_mat_from_cs_vecs(&[
+                CsVec::new(3, vec![], vec![]),
+                CsVec::new(3, vec![], vec![]),
+            ]),
+        };
+        let stats = IndexStats::build(&fields.map(|_, x| x.view()));
+        assert_eq!(stats.n_docs, 2);
+        // 同一文档的多个字段只算一次
+        assert_eq!(stats.df, vec![1, 1, 1]);
+        assert_eq!(stats.field_len.title, 2);
+        assert_eq!(stats.field_len.text, 3);
+        assert!((stats.avg_len() - 2.5).abs() < 1e-6);
+
+        // 分段累加与整体计算一致，删除后扣除
+        let mut sum = IndexStats::default();
+        sum.add(&stats);
+        sum.add(&stats);
+        assert_eq!(sum.n_docs, 4);
+        assert_eq!(sum.df, vec![2, 2, 2]);
+        sum.remove_doc(&fields.map(|_, x| x.outer_view(0).unwrap()));
+        assert_eq!(sum.df, vec![1, 2, 1]);
+        assert_eq!(sum.field_len.text, 3);
+    }
+}
@@ -1,11 +1,37 @@
-use sprs::{CsMat, CsMatView, CsVec, CsVecView};
+use serde::{Deserialize, Serialize};
+use sprs::{CsMatView, CsVec, CsVecView};
 
-use crate::core::cs_helper;
+/// 每块的文档数，每块记录一个跳表指针
+const BLOCK_SIZE: usize = 128;
 
-#[derive(Debug)]
+/// 跳表指针：一块文档的最后一个文档和这一块在压缩数据中的起始位置
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+struct Skip {
+    /// 这一块的最后一个文档
+    last: usize,
+    /// 这一块的起始位置（相对于词项的压缩数据）
+    offset: usize,
+}
+
+/// 压缩的倒排索引
+///
+/// 每个词项的文档列表（递增）按差值（与前一个文档之差，第一个为文档本身）做变长字节编码，
+/// 每个文档之后是词项在其中的词频（同样变长编码）；
+/// 每 [`BLOCK_SIZE`] 个文档一块，用跳表指针跳过不需要的块；搜索时逐个解码，不复制文档列表
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
 pub struct InvertedIndex {
-    /// 倒排索引，值表示存在性，shape(nt,nd)
-    data: CsMat<bool>,
+    /// 文档数
+    nd: usize,
+    /// 每个词项的文档在所有文档列表中的起始位置（前缀和，相邻之差为文档数）, shape(nt+1,)
+    doc_ptr: Vec<usize>,
+    /// 每个词项的压缩数据在 `bytes` 中的起始位置, shape(nt+1,)
+    byte_ptr: Vec<usize>,
+    /// 每个词项的跳表指针在 `skips` 中的起始位置, shape(nt+1,)
+    skip_ptr: Vec<usize>,
+    /// 所有词项的压缩的文档列表和词频
+    bytes: Vec<u8>,
+    /// 所有词项的跳表指针
+    skips: Vec<Skip>,
 }
 
 impl InvertedIndex {
@@ -14,14 +40,71 @@ impl InvertedIndex {
         tracing::info!("[InvertedIndex] 开始构建索引");
         let start_time = std::time::Instant::now();
 
-        let data = x.map(|&v| v > 0).transpose_into().into_csr();
+        let (nd, nt) = x.shape();
+        let counts = x.transpose_view().to_csr();
+
+        let mut index = InvertedIndex {
+            nd,
+            doc_ptr: Vec::with_capacity(nt + 1),
+            byte_ptr: Vec::with_capacity(nt + 1),
+            skip_ptr: Vec::with_capacity(nt + 1),
+            bytes: vec![],
+            skips: vec![],
+        };
+        index.doc_ptr.push(0);
+        index.byte_ptr.push(0);
+        index.skip_ptr.push(0);
+        for docs in counts.outer_iterator() {
+            // 压缩的文档列表、词频和跳表指针
+            let start = index.bytes.len();
+            let mut prev = 0;
+            for (block, tfs) in docs
+                .indices()
+                .chunks(BLOCK_SIZE)
+                .zip(docs.data().chunks(BLOCK_SIZE))
+            {
+                index.skips.push(Skip {
+                    last: *block.last().unwrap(),
+                    offset: index.bytes.len() - start,
+                });
+                for (&d, &tf) in block.iter().zip(tfs) {
+                    encode_varint(d - prev, &mut index.bytes);
+                    encode_varint(tf, &mut index.bytes);
+                    prev = d;
+                }
+            }
+
+            index
+                .doc_ptr
+                .push(index.doc_ptr.last().unwrap() + docs.nnz());
+            index.byte_ptr.push(index.bytes.len());
+            index.skip_ptr.push(index.skips.len());
+        }
 
         tracing::info!(
-            "[InvertedIndex] 构建索引完成，用时 {:?}，稀疏度 {}",
+            "[InvertedIndex] 构建索引完成，用时 {:?}，{} 条倒排记录，压缩后 {} 字节",
             start_time.elapsed(),
-            data.density()
+            counts.nnz(),
+            index.bytes.len()
         );
-        InvertedIndex { data }
+        index
+    }
+
+    /// 词项数
+    fn nt(&self) -> usize {
+        self.doc_ptr.len().saturating_sub(1)
+    }
+
+    /// 词项t的文档列表（递增，逐个解码），索引构建之后才出现的词项没有文档
+    pub fn postings(&self, t: usize) -> Postings<'_> {
+        if t >= self.nt() {
+            return Postings::new(&[], &[], 0);
+        }
+        Postings::new(
+            &self.bytes[self.byte_ptr[t]..self.byte_ptr[t + 1]],
+            &self.skips[self.skip_ptr[t]..self.skip_ptr[t + 1]],
+            self.doc_ptr[t + 1] - self.doc_ptr[t],
+        )
     }
 
     /// * `x`: 文档词频, shape(nt,)
@@ -30,40 +113,235 @@ impl InvertedIndex {
         tracing::info!("[InvertedIndex] 搜索");
         let start_time = std::time::Instant::now();
 
-        let (_nt, nd) = self.data.shape();
-        let mut result: Option<CsVec<bool>> = None;
+        let nd = self.nd;
+        let empty = || CsVec::new(nd, vec![], vec![]);
 
-        // 遍历 nnz 的词项
+        // nnz 的词项的文档列表 (Posting List)
+        let mut lists = vec![];
         for (t, &v) in x.iter() {
             if v == 0 {
                 continue; // 跳过词频为 0 的词项 (其实理论上稀疏矩阵里不会有，但是稀疏矩阵确实可以存储 0，也算做 nnz)
             }
+            // 索引构建之后才出现的词项没有文档
+            if t >= self.nt() {
+                return empty();
+            }
+            lists.push(self.postings(t));
+        }
 
-            // 取出 t 对应的文档列表 (Posting List)
-            let docs = self.data.outer_view(t).unwrap().to_owned();
+        // 遍历最短的文档列表，其余的用跳表指针跳到这个文档
+        lists.sort_by_key(Postings::len);
+        let Some((shortest, rest)) = lists.split_first_mut() else {
+            return empty();
+        };
+        let mut docs = vec![];
+        'outer: for d in shortest {
+            for list in rest.iter_mut() {
+                match list.advance(d) {
+                    Some(next) if next == d => {}
+                    Some(_) => continue 'outer,
+                    None => break 'outer,
+                }
+            }
+            docs.push(d);
+        }
+        tracing::info!("[InvertedIndex] 搜索完成，用时 {:?}", start_time.elapsed());
 
-            result = Some(match result {
-                Some(old) => cs_helper::intersection(old.view(), docs.view()),
-                None => docs,
-            })
+        let len = docs.len();
+        CsVec::new(nd, docs, vec![true; len])
+    }
+
+    /// * `x`: 文档词频, shape(nt,)
+    /// * `min_match`: 文档至少要包含的词项数
+    /// * `returns`: 包含至少 `min_match` 个词项t的文档, shape(nd,)
+    pub fn search_min_match(&self, x: CsVecView<usize>, min_match: usize) -> CsVec<bool> {
+        tracing::info!("[InvertedIndex] 搜索（至少匹配 {} 个词项）", min_match);
+        let start_time = std::time::Instant::now();
+
+        // 所有词项的文档列表合并在一起（会重复）
+        let mut docs = x
+            .iter()
+            .filter(|&(_t, &v)| v > 0)
+            .flat_map(|(t, _)| self.postings(t))
+            .collect::<Vec<_>>();
+        docs.sort_unstable();
+
+        // 每个文档包含的词项数
+        let mut result = vec![];
+        for run in docs.chunk_by(|a, b| a == b) {
+            if run.len() >= min_match.max(1) {
+                result.push(run[0]);
+            }
         }
+
         tracing::info!("[InvertedIndex] 搜索完成，用时 {:?}", start_time.elapsed());
 
-        result.unwrap_or(CsVec::new(nd, vec![], vec![]))
+        let len = result.len();
+        CsVec::new(self.nd, result, vec![true; len])
+    }
+}
+
+/// 变长字节编码：每字节低 7 位为数据，最高位为 1 表示后面还有字节
+fn encode_varint(mut v: usize, bytes: &mut Vec<u8>) {
+    while v >= 0x80 {
+        bytes.push((v as u8 & 0x7f) | 0x80);
+        v >>= 7;
+    }
+    bytes.push(v as u8);
+}
+
+/// * `pos`: 读取的位置，读取后移动到下一个数
+fn decode_varint(bytes: &[u8], pos: &mut usize) -> usize {
+    let mut v = 0;
+    let mut shift = 0;
+    loop {
+        let byte = bytes[*pos];
+        *pos += 1;
+        v |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return v;
+        }
+        shift += 7;
+    }
+}
+
+/// 一个词项的文档列表，按需逐个解码
+///
+/// 作为迭代器依次返回文档；[`Postings::doc`] 查看当前文档，[`Postings::tf`] 查看词项在当前文档中的词频，
+/// [`Postings::advance`] 用跳表指针跳到不小于目标的文档
+#[derive(Debug, Clone)]
+pub struct Postings<'a> {
+    bytes: &'a [u8],
+    skips: &'a [Skip],
+    /// 文档数
+    len: usize,
+    /// 下一个文档在 `bytes` 中的位置
+    pos: usize,
+    /// 当前块
+    block: usize,
+    /// 当前块已解码的文档数
+    decoded: usize,
+    /// 当前文档，`None` 表示已经遍历完
+    current: Option<usize>,
+    /// 词项在当前文档中的词频
+    tf: usize,
+}
+
+impl<'a> Postings<'a> {
+    fn new(bytes: &'a [u8], skips: &'a [Skip], len: usize) -> Self {
+        let mut postings = Self {
+            bytes,
+            skips,
+            len,
+            pos: 0,
+            block: 0,
+            decoded: 0,
+            current: None,
+            tf: 0,
+        };
+        postings.current = postings.decode_next(0);
+        postings
+    }
+
+    /// 文档数
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 当前文档，`None` 表示已经遍历完
+    pub fn doc(&self) -> Option<usize> {
+        self.current
+    }
+
+    /// 词项在当前文档中的词频，已经遍历完时无意义
+    pub fn tf(&self) -> usize {
+        self.tf
+    }
+
+    /// 第k块的文档数
+    fn block_len(&self, k: usize) -> usize {
+        match k + 1 < self.skips.len() {
+            true => BLOCK_SIZE,
+            false => self.len - BLOCK_SIZE * k,
+        }
+    }
+
+    /// 解码下一个文档及其词频
+    /// * `prev`: 前一个文档
+    fn decode_next(&mut self, prev: usize) -> Option<usize> {
+        if self.block >= self.skips.len() {
+            return None;
+        }
+        if self.decoded == self.block_len(self.block) {
+            self.block += 1;
+            self.decoded = 0;
+            if self.block >= self.skips.len() {
+                return None;
+            }
+        }
+        self.decoded += 1;
+        let d = prev + decode_varint(self.bytes, &mut self.pos);
+        self.tf = decode_varint(self.bytes, &mut self.pos);
+        Some(d)
+    }
+
+    /// 移动到第一个不小于 `target` 的文档并返回，没有时返回 `None`
+    ///
+    /// 先用跳表指针找到最后一个文档不小于 `target` 的块，再在块内逐个解码
+    pub fn advance(&mut self, target: usize) -> Option<usize> {
+        let current = self.current?;
+        if current >= target {
+            return Some(current);
+        }
+
+        let k = self.block + self.skips[self.block..].partition_point(|s| s.last < target);
+        if k >= self.skips.len() {
+            self.block = self.skips.len();
+            self.current = None;
+            return None;
+        }
+        if k > self.block {
+            // 跳到第k块，前一个文档为上一块的最后一个文档
+            self.block = k;
+            self.decoded = 0;
+            self.pos = self.skips[k].offset;
+            self.current = self.decode_next(self.skips[k - 1].last);
+        }
+        while let Some(d) = self.current {
+            if d >= target {
+                break;
+            }
+            self.current = self.decode_next(d);
+        }
+        self.current
+    }
+}
+
+impl Iterator for Postings<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let d = self.current?;
+        self.current = self.decode_next(d);
+        Some(d)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use sprs::CsVec;
+    use crate::core::cs_helper;
 
     #[test]
     fn test_inverted_index() {
         let x = cs_helper::cs_mat_from_cs_vecs(&[
-            CsVec::new(3, vec![0, 1], vec![1, 1]),
+            CsVec::new(3, vec![0, 1], vec![2, 1]),
             CsVec::new(3, vec![1, 2], vec![1, 1]),
-            CsVec::new(3, vec![0, 2], vec![1, 1]),
+            CsVec::new(3, vec![0, 2], vec![1, 3]),
             CsVec::new(3, vec![0, 1, 2], vec![1, 1, 1]),
         ]);
         let index = InvertedIndex::build(x.view());
@@ -73,5 +351,70 @@ mod tests {
         assert_eq!(result.nnz(), 2);
         assert_eq!(result.indices(), vec![0, 3]);
         assert_eq!(result.data(), vec![true, true]);
+
+        let result = index.search_min_match(x.view(), 1);
+        assert_eq!(result.indices(), vec![0, 1, 2, 3]);
+
+        let x = CsVec::new(3, vec![0, 1, 2], vec![1, 1, 1]);
+        let result = index.search_min_match(x.view(), 2);
+        assert_eq!(result.indices(), vec![0, 1, 2, 3]);
+        let result = index.search_min_match(x.view(), 3);
+        assert_eq!(result.indices(), vec![3]);
+
+        assert_eq!(index.postings(0).collect::<Vec<_>>(), vec![0, 2, 3]);
+        // 每个文档的词频
+        let mut postings = index.postings(0);
+        let mut tfs = vec![];
+        while let Some(d) = postings.doc() {
+            tfs.push((d, postings.tf()));
+            postings.next();
+        }
+        assert_eq!(tfs, vec![(0, 2), (2, 1), (3, 1)]);
+        let mut postings = index.postings(2);
+        assert_eq!(postings.advance(2), Some(2));
+        assert_eq!(postings.tf(), 3);
+        // 索引构建之后才出现的词项
+        assert_eq!(index.postings(3).count(), 0);
+
+        // 跨越多块的长文档列表
+        let nd = 2000;
+        let expected = |t: usize| {
+            (0..nd)
+                .filter(|d| d % (t + 1) == 0 || d > &1990)
+                .collect::<Vec<_>>()
+        };
+        let rows = (0..nd)
+            .map(|d| {
+                let terms = (0..4)
+                    .filter(|&t| expected(t).contains(&d))
+                    .collect::<Vec<_>>();
+                let tfs = terms.iter().map(|&t| t + 1).collect();
+                CsVec::new(4, terms, tfs)
+            })
+            .collect::<Vec<_>>();
+        let index = InvertedIndex::build(cs_helper::cs_mat_from_cs_vecs(&rows).view());
+        for t in 0..4 {
+            assert_eq!(index.postings(t).len(), expected(t).len());
+            assert_eq!(index.postings(t).collect::<Vec<_>>(), expected(t));
+        }
+        // 跳到不小于目标的文档
+        let mut postings = index.postings(3);
+        assert_eq!(postings.advance(0), Some(0));
+        assert_eq!(postings.advance(1), Some(4));
+        assert_eq!(postings.advance(1001), Some(1004));
+        assert_eq!(postings.tf(), 4);
+        assert_eq!(postings.advance(1004), Some(1004));
+        assert_eq!(postings.next(), Some(1004));
+        assert_eq!(postings.doc(), Some(1008));
+        assert_eq!(postings.advance(1989), Some(1991));
+        assert_eq!(postings.advance(nd), None);
+        assert_eq!(postings.next(), None);
+
+        let x = CsVec::new(4, vec![1, 2, 3], vec![1, 1, 1]);
+        let result = index.search(x.view());
+        let brute = (0..nd)
+            .filter(|&d| d % 12 == 0 || d > 1990)
+            .collect::<Vec<_>>();
+        assert_eq!(result.indices(), brute);
     }
 }
@@ -0,0 +1,94 @@
+use crate::core::Segment;
+use std::{collections::HashSet, ops::Range, sync::Arc};
+
+/// 分层合并策略
+///
+/// 按未删除的文档数把段分层：不超过 `floor_segment_size` 的在第 0 层，
+/// 之后每层的大小上限是上一层的 `segments_per_tier` 倍。
+/// 同一层有 `segments_per_tier` 个相邻的段时合并它们（只合并相邻的段，保证段之间的文档id有序）；
+/// 已删除文档比例过高的段单独重写
+#[derive(Debug, Clone)]
+pub struct TieredMergePolicy {
+    /// 每层的段数达到多少时合并
+    segments_per_tier: usize,
+    /// 第 0 层的段大小上限（文档数）
+    floor_segment_size: usize,
+    /// 段中已删除文档的比例超过多少时重写
+    max_deleted_ratio: f64,
+}
+
+impl TieredMergePolicy {
+    pub fn new(
+        segments_per_tier: usize,
+        floor_segment_size: usize,
+        max_deleted_ratio: f64,
+    ) -> Self {
+        Self {
+            segments_per_tier: segments_per_tier.max(2),
+            floor_segment_size: floor_segment_size.max(1),
+            max_deleted_ratio,
+        }
+    }
+
+    /// 段所在的层
+    /// * `size`: 段中未删除的文档数
+    pub fn tier(&self, size: usize) -> usize {
+        let mut tier = 0;
+        let mut limit = self.floor_segment_size;
+        while size > limit {
+            limit = limit.saturating_mul(self.segments_per_tier);
+            tier += 1;
+        }
+        tier
+    }
+
+    /// 选出要合并的段
+    /// * `segments`: 按文档id递增排列的段
+    /// * `deleted`: 已删除的文档
+    /// * `returns`: 要合并的相邻段的范围，不需要合并时返回 `None`
+    pub fn find_merge(
+        &self,
+        segments: &[Arc<Segment>],
+        deleted: &HashSet<usize>,
+    ) -> Option<Range<usize>> {
+        let live = segments
+            .iter()
+            .map(|s| s.len() - s.num_deleted(deleted))
+            .collect::<Vec<_>>();
+
+        // 同一层的相邻段
+        let mut start = 0;
+        for i in 1..=segments.len() {
+            if i == segments.len() || self.tier(live[i]) != self.tier(live[start]) {
+                if i - start >= self.segments_per_tier {
+                    return Some(start..start + self.segments_per_tier);
+                }
+                start = i;
+            }
+        }
+
+        // 已删除文档过多的段
+        segments
+            .iter()
+            .zip(&live)
+            .position(|(s, &live)| {
+                (s.len() - live) as f64 > s.len() as f64 * self.max_deleted_ratio
+            })
+            .map(|i| i..i + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tier() {
+        let policy = TieredMergePolicy::new(4, 10, 0.3);
+        assert_eq!(policy.tier(0), 0);
+        assert_eq!(policy.tier(10), 0);
+        assert_eq!(policy.tier(11), 1);
+        assert_eq!(policy.tier(40), 1);
+        assert_eq!(policy.tier(41), 2);
+    }
+}
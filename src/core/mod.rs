@@ -1,53 +1,379 @@
+mod analyzer;
+mod bm25_scorer;
+mod bm25f_scorer;
 mod count_vectorizer;
 pub mod cs_helper;
+mod decay;
+mod explain;
+mod facets;
+mod field;
+mod filter_index;
+mod highlighter;
+mod index_stats;
 mod inverted_index;
+mod merge_policy;
+mod normalizer;
+mod persist;
+mod positional_index;
+mod query;
+mod segment;
+mod similar;
+mod spell;
+mod stopwords;
+mod suggest;
 mod tfidf_vectorizer;
+mod top_k;
+mod update;
 mod vocabulary;
 
-use crate::dataset::Dataset;
+use crate::{
+    config::{Config, UserWord},
+    dataset::{Dataset, Doc},
+};
+pub use analyzer::*;
+pub use bm25_scorer::*;
+pub use bm25f_scorer::*;
 pub use count_vectorizer::*;
+pub use decay::*;
+pub use explain::*;
+pub use facets::*;
+pub use field::*;
+pub use filter_index::*;
+pub use highlighter::*;
+pub use index_stats::*;
 pub use inverted_index::*;
+pub use merge_policy::*;
+pub use normalizer::*;
+pub use persist::*;
+pub use positional_index::*;
+pub use query::*;
+use rayon::prelude::*;
+pub use segment::*;
+use serde::{Deserialize, Serialize};
+pub use spell::*;
+use sprs::{CsVec, CsVecView};
+use std::{collections::HashSet, ops::Range, sync::Arc};
+pub use stopwords::*;
+pub use suggest::*;
 pub use tfidf_vectorizer::*;
+pub use top_k::*;
+pub use update::*;
 pub use vocabulary::*;
 
+#[derive(Deserialize, Serialize)]
 pub struct Core {
+    /// 分词器和所有段共享的词汇表
     pub count_vectorizer: CountVectorizer,
-    pub index: InvertedIndex,
+    /// 索引段，按文档id递增排列
+    pub segments: Vec<Arc<Segment>>,
+    /// 所有段的统计量之和（不含已删除的文档），得分与一次性构建的索引相同
+    pub stats: IndexStats,
+    /// 文档总数（含已删除、未被合并清除的文档），也是下一个新文档的id
+    pub num_docs: usize,
     pub tfidf_vectorizer: TfidfVectorizer,
+    pub bm25_scorer: Bm25Scorer,
+    pub bm25f_scorer: Bm25fScorer,
+    /// 每个词项得分的上界，用于 top-k 检索
+    pub max_scores: MaxScores,
+    /// 类别、单位、发布年份的倒排表，用于计算得分之前过滤
+    pub filter_index: FilterIndex,
+    /// 标题片段的倒排表，用于补全
+    pub title_fragments: TitleFragments,
+    /// 已删除（墓碑）的文档，搜索时排除；合并段时文档被清除，墓碑随之去掉
+    pub deleted: HashSet<usize>,
+    /// 构建索引时使用的 jieba 用户词典，词典变化时据此找出需要重新分词的文档
+    pub user_dict: Vec<UserWord>,
 }
 
 impl Core {
-    pub fn new(dataset: &Dataset) -> anyhow::Result<Self> {
-        // 可以搜索的内容
-        let text_for_search = dataset
-            .docs
+    pub fn new(dataset: &Dataset, config: &Config) -> anyhow::Result<Self> {
+        let mut core = Self {
+            count_vectorizer: CountVectorizer::with_analyzer(AnalyzerChain::from_config(
+                &config.analyzer,
+            )),
+            segments: vec![],
+            stats: IndexStats::default(),
+            num_docs: 0,
+            tfidf_vectorizer: TfidfVectorizer::new(),
+            bm25_scorer: Bm25Scorer::new(config.bm25.k1, config.bm25.b),
+            bm25f_scorer: Bm25fScorer::new(config.bm25f.k1, config.bm25f.b),
+            max_scores: MaxScores::default(),
+            filter_index: FilterIndex::default(),
+            title_fragments: TitleFragments::default(),
+            deleted: HashSet::new(),
+            user_dict: config.analyzer.user_words.clone(),
+        };
+        core.add_segment(&dataset.docs);
+        core.refresh();
+
+        Ok(core)
+    }
+
+    /// 对新文档分词，作为一个新段加入，新文档的id从当前文档数开始
+    ///
+    /// 不会更新得分用的 IDF 等，之后需要调用 [`Core::refresh`]
+    fn add_segment(&mut self, docs: &[Doc]) {
+        if docs.is_empty() {
+            return;
+        }
+        let base = self.num_docs;
+
+        // 可以搜索的内容，按字段分开
+        let text_for_search = PerField::from_fn(|field| {
+            docs.iter()
+                .map(|doc| field.extract(doc))
+                .collect::<Vec<_>>()
+        });
+
+        // 并行分词，记录每个文档被停用词过滤去掉的词项（所有字段）
+        tracing::info!("[Core] 分词");
+        let phase_time = std::time::Instant::now();
+        let mut removed = vec![HashSet::new(); docs.len()];
+        let analyzed = PerField::from_fn(|field| {
+            let (tokens, terms): (Vec<_>, Vec<_>) = text_for_search[field]
+                .par_iter()
+                .map(|s| self.count_vectorizer.analyze_with_removed(s))
+                .unzip();
+            for (removed, terms) in removed.iter_mut().zip(terms) {
+                removed.extend(terms);
+            }
+            tokens
+        });
+        let removed_postings = removed.iter().map(HashSet::len).collect::<Vec<_>>();
+        tracing::info!(
+            "[Core] 分词完成，用时 {:?}，{} 个线程",
+            phase_time.elapsed(),
+            rayon::current_num_threads()
+        );
+
+        let mut field_data = self
+            .count_vectorizer
+            .partial_fit(&[&analyzed.title, &analyzed.text, &analyzed.attachments])
+            .into_iter();
+        let field_data = PerField::from_fn(|_| field_data.next().unwrap());
+        let positions = analyzed
+            .map(|_, analyzed| PositionalIndex::build(self.count_vectorizer.vocab(), analyzed));
+
+        let segment = Segment::new(
+            (base..base + docs.len()).collect(),
+            field_data,
+            positions,
+            removed_postings,
+        );
+        tracing::info!(
+            "[Core] 新增段，{} 个文档，停用词过滤去掉 {} 条倒排记录，共 {} 个段",
+            segment.len(),
+            segment.stats.removed_postings,
+            self.segments.len() + 1
+        );
+        self.stats.add(&segment.stats);
+        self.segments.push(Arc::new(segment));
+        self.filter_index.add(base, docs);
+        self.title_fragments
+            .add(base, &text_for_search.title, &analyzed.title);
+        self.num_docs += docs.len();
+    }
+
+    /// 根据全局统计量重新计算得分用的 IDF、文档向量的模长和平均长度，以及词项得分的上界
+    fn refresh(&mut self) {
+        self.tfidf_vectorizer.fit(&self.stats);
+        self.tfidf_vectorizer
+            .fit_norms(&self.segments, self.num_docs);
+        self.bm25_scorer.fit(&self.stats);
+        self.bm25f_scorer.fit(&self.stats);
+        self.max_scores = MaxScores::build(
+            &self.segments,
+            self.count_vectorizer.vocab().len(),
+            &self.tfidf_vectorizer,
+            &self.bm25_scorer,
+            &self.bm25f_scorer,
+        );
+    }
+
+    /// 用合并后的段替换若干相邻的段，并压缩文档id：合并时清除的文档从数据集中去掉，其后的文档id依次前移
+    /// * `range`: 被合并的段的范围
+    /// * `sources`: 被合并的段，与 `range` 中的段不同（已被替换）时放弃
+    /// * `merged`: 合并后的段，为 `None` 表示其中的文档都已删除
+    /// * `returns`: 是否替换成功
+    pub fn replace_segments(
+        &mut self,
+        dataset: &mut Dataset,
+        range: Range<usize>,
+        sources: &[Arc<Segment>],
+        merged: Option<Segment>,
+    ) -> bool {
+        let current = self.segments.get(range.clone()).unwrap_or_default();
+        if current.len() != sources.len()
+            || current.iter().zip(sources).any(|(a, b)| !Arc::ptr_eq(a, b))
+        {
+            return false;
+        }
+
+        // 合并时清除的文档（递增）
+        let merged_ids = merged.as_ref().map_or(&[][..], |s| s.doc_ids());
+        let purged = sources
             .iter()
-            .map(|doc| {
-                format!(
-                    "{}\n{}\n{}",
-                    doc.title,
-                    doc.text,
-                    doc.attachments
-                        .iter()
-                        .map(|a| a.name.clone())
-                        .collect::<Vec<_>>()
-                        .join("\n")
-                )
-            })
+            .flat_map(|s| s.doc_ids())
+            .copied()
+            .filter(|d| merged_ids.binary_search(d).is_err())
+            .collect::<Vec<_>>();
+        self.segments.splice(range, merged.map(Arc::new));
+        self.compact(dataset, &purged);
+        true
+    }
+
+    /// 从数据集和索引中去掉合并时清除的文档，其后的文档id前移
+    /// * `purged`: 清除的文档（递增）
+    fn compact(&mut self, dataset: &mut Dataset, purged: &[usize]) {
+        let Some(&first) = purged.first() else {
+            return;
+        };
+        remove_ids(&mut dataset.docs, purged);
+        for segment in &mut self.segments {
+            if segment.doc_ids().last().is_some_and(|&last| last > first) {
+                Arc::make_mut(segment).compact_ids(purged);
+            }
+        }
+        let mut deleted = self.deleted.drain().collect::<Vec<_>>();
+        deleted.sort_unstable();
+        compact_ids(&mut deleted, purged);
+        self.deleted = deleted.into_iter().collect();
+        self.tfidf_vectorizer.compact(purged);
+        self.filter_index.compact(purged);
+        self.title_fragments.compact(purged);
+        self.num_docs -= purged.len();
+        tracing::info!(
+            "[Core] 清除 {} 个已删除的文档，剩余 {} 个文档",
+            purged.len(),
+            self.num_docs
+        );
+    }
+
+    /// 文档d所在的段和段内id，不在任何段中（已被合并清除）时返回 `None`
+    pub fn locate(&self, d: usize) -> Option<(&Segment, usize)> {
+        locate(&self.segments, d)
+    }
+
+    /// 在所有段中检索，返回未删除的文档
+    /// * `x`: 搜索词词频, shape(nt,)
+    /// * `field`: 限定字段，`None` 表示所有字段
+    /// * `min_match`: 至少匹配的词项数，`None` 表示全部匹配
+    /// * `returns`: shape(nd,)
+    pub fn search(
+        &self,
+        x: CsVecView<usize>,
+        field: Option<Field>,
+        min_match: Option<usize>,
+    ) -> CsVec<bool> {
+        // 段按文档id递增排列，结果直接拼接即可
+        let docs = self
+            .segments
+            .iter()
+            .flat_map(|segment| segment.search(x, field, min_match))
+            .filter(|&d| !self.is_deleted(d))
             .collect::<Vec<_>>();
+        let len = docs.len();
+        CsVec::new(self.num_docs, docs, vec![true; len])
+    }
 
-        let mut count_vectorizer = CountVectorizer::new();
-        count_vectorizer.fit(&text_for_search);
+    /// 搜索词项在每个文档中的词频，逐段遍历词项的倒排记录，不读取文档的词频向量
+    /// * `terms`: 搜索词项
+    /// * `docs`: 文档的全局id（递增）
+    /// * `returns`: 每个文档中出现的搜索词项及其词频, shape(docs.len(),)
+    pub fn term_freqs(&self, terms: &[usize], docs: &[usize]) -> Vec<Vec<(usize, usize)>> {
+        let mut freqs = Vec::with_capacity(docs.len());
+        let mut docs = docs;
+        for segment in &self.segments {
+            // 段按文档id递增排列，取出这个段中的文档
+            let last = segment.doc_ids().last().copied();
+            let n = docs.partition_point(|&d| last.is_some_and(|last| d <= last));
+            let (in_segment, rest) = docs.split_at(n);
+            docs = rest;
 
-        let index = InvertedIndex::build(count_vectorizer.get_data());
+            let mut lists = terms
+                .iter()
+                .map(|&t| (t, segment.index.postings(t)))
+                .collect::<Vec<_>>();
+            for &d in in_segment {
+                let Some(i) = segment.local_id(d) else {
+                    freqs.push(vec![]);
+                    continue;
+                };
+                let matched = lists
+                    .iter_mut()
+                    .filter_map(|(t, list)| (list.advance(i) == Some(i)).then(|| (*t, list.tf())))
+                    .collect();
+                freqs.push(matched);
+            }
+        }
+        // 不在任何段中的文档
+        freqs.resize(freqs.len() + docs.len(), vec![]);
+        freqs
+    }
 
-        let mut tfidf_vectorizer = TfidfVectorizer::new();
-        tfidf_vectorizer.fit(count_vectorizer.get_data());
+    /// 文档d是否已删除
+    pub fn is_deleted(&self, d: usize) -> bool {
+        self.deleted.contains(&d)
+    }
 
-        Ok(Self {
-            count_vectorizer,
-            index,
-            tfidf_vectorizer,
-        })
+    /// 短语转换为 (词项id, 相对位置) 列表，有词项不在词汇表中时返回 `None`
+    pub fn phrase(&self, phrase: &str) -> Option<Vec<(usize, usize)>> {
+        let tokens = self.count_vectorizer.analyze(phrase);
+        let p0 = tokens.first().map_or(0, |t| t.position);
+        // 只用完整的词判断相邻，子词与所在的词位置相同
+        full_words(&tokens)
+            .map(|t| {
+                let id = self.count_vectorizer.vocab().id(&t.term)?;
+                Some((id, t.position - p0))
+            })
+            .collect()
     }
+
+    /// 文档d中是否包含短语
+    /// * `field`: 限定字段，`None` 表示任意字段
+    pub fn contains_phrase(
+        &self,
+        phrase: &[(usize, usize)],
+        d: usize,
+        field: Option<Field>,
+    ) -> bool {
+        let Some((segment, i)) = self.locate(d) else {
+            return false;
+        };
+        match field {
+            Some(f) => segment.fields[f].positions.contains_phrase(phrase, i),
+            None => Field::ALL
+                .iter()
+                .any(|&f| segment.fields[f].positions.contains_phrase(phrase, i)),
+        }
+    }
+}
+
+/// 文档d所在的段和段内id
+fn locate(segments: &[Arc<Segment>], d: usize) -> Option<(&Segment, usize)> {
+    // 第一个最后一个文档id不小于d的段
+    let i = segments.partition_point(|s| s.doc_ids().last().is_some_and(|&last| last < d));
+    let segment = segments.get(i)?;
+    Some((segment, segment.local_id(d)?))
+}
+
+/// 去掉已清除的文档，其后的文档id前移
+/// * `ids`: 文档id（递增）
+/// * `purged`: 清除的文档（递增）
+fn compact_ids(ids: &mut Vec<usize>, purged: &[usize]) {
+    ids.retain(|d| purged.binary_search(d).is_err());
+    for d in ids {
+        *d -= purged.partition_point(|&p| p < *d);
+    }
+}
+
+/// 去掉已清除的文档对应的元素
+/// * `values`: 按文档id排列, shape(nd,)
+/// * `purged`: 清除的文档（递增）
+fn remove_ids<T>(values: &mut Vec<T>, purged: &[usize]) {
+    let mut d = 0;
+    values.retain(|_| {
+        d += 1;
+        purged.binary_search(&(d - 1)).is_err()
+    });
 }
@@ -0,0 +1,159 @@
+use crate::{config::NormalizeConfig, core::CharFilter};
+use caseless::Caseless;
+use unicode_normalization::{char::canonical_combining_class, UnicodeNormalization};
+
+/// Unicode 规范化：NFKC、全角转半角、繁体转简体、大小写折叠（按此顺序）
+///
+/// 转换后的字符都对应到原文中来自的字符，高亮等仍按原文位置
+#[derive(Debug, Clone, Default)]
+pub struct UnicodeNormalizer {
+    pub nfkc: bool,
+    pub width: bool,
+    pub traditional_to_simplified: bool,
+    pub case_fold: bool,
+}
+
+impl UnicodeNormalizer {
+    pub fn from_config(config: &NormalizeConfig) -> Self {
+        Self {
+            nfkc: config.nfkc,
+            width: config.width,
+            traditional_to_simplified: config.traditional_to_simplified,
+            case_fold: config.case_fold,
+        }
+    }
+
+    /// 是否不做任何转换
+    pub fn is_empty(&self) -> bool {
+        !(self.nfkc || self.width || self.traditional_to_simplified || self.case_fold)
+    }
+}
+
+impl CharFilter for UnicodeNormalizer {
+    fn filter(&self, mut chars: Vec<(char, usize)>) -> Vec<(char, usize)> {
+        if self.nfkc {
+            chars = nfkc(chars);
+        }
+        if self.width {
+            for (c, _) in &mut chars {
+                *c = fold_width(*c);
+            }
+        }
+        if self.traditional_to_simplified {
+            simplify(&mut chars, fast2s::convert);
+        }
+        if self.case_fold {
+            chars = chars
+                .into_iter()
+                .flat_map(|(c, i)| std::iter::once(c).default_case_fold().map(move |c| (c, i)))
+                .collect();
+        }
+        chars
+    }
+}
+
+/// NFKC，基字符与其后的组合字符一起转换，结果对应到基字符的位置
+fn nfkc(chars: Vec<(char, usize)>) -> Vec<(char, usize)> {
+    let mut normalized = Vec::with_capacity(chars.len());
+    let mut cluster = String::new();
+    let mut origin = 0;
+    for (c, i) in chars {
+        if canonical_combining_class(c) == 0 && !cluster.is_empty() {
+            normalized.extend(cluster.nfkc().map(|c| (c, origin)));
+            cluster.clear();
+        }
+        if cluster.is_empty() {
+            origin = i;
+        }
+        cluster.push(c);
+    }
+    normalized.extend(cluster.nfkc().map(|c| (c, origin)));
+    normalized
+}
+
+/// 繁体转简体：整段转换（参考前后的字）；字数变化时无法对应原文位置，改为逐字转换，
+/// 一个字转换为多个字时保留原字
+/// * `convert`: 繁简转换
+fn simplify(chars: &mut [(char, usize)], convert: impl Fn(&str) -> String) {
+    let text = chars.iter().map(|&(c, _)| c).collect::<String>();
+    let simplified = convert(&text).chars().collect::<Vec<_>>();
+    if simplified.len() == chars.len() {
+        for ((c, _), simplified) in chars.iter_mut().zip(simplified) {
+            *c = simplified;
+        }
+        return;
+    }
+    for (c, _) in chars {
+        let converted = convert(c.encode_utf8(&mut [0; 4]));
+        let mut converted = converted.chars();
+        if let (Some(simplified), None) = (converted.next(), converted.next()) {
+            *c = simplified;
+        }
+    }
+}
+
+/// 全角 ASCII 字符（U+FF01..U+FF5E）和全角空格转为半角
+fn fold_width(c: char) -> char {
+    match c {
+        '\u{3000}' => ' ',
+        '\u{ff01}'..='\u{ff5e}' => char::from_u32(c as u32 - 0xfee0).unwrap_or(c),
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::AnalyzerConfig,
+        core::{Analyzer, AnalyzerChain},
+    };
+
+    fn normalize(normalizer: &UnicodeNormalizer, text: &str) -> (String, Vec<usize>) {
+        let chars = text.chars().enumerate().map(|(i, c)| (c, i)).collect();
+        normalizer.filter(chars).into_iter().unzip()
+    }
+
+    #[test]
+    fn test_normalizer() {
+        let normalizer = UnicodeNormalizer::from_config(&NormalizeConfig::default());
+        // 全角转半角、组合字符合成、大小写折叠
+        assert_eq!(
+            normalize(&normalizer, "ＡＩ　e\u{301}Straße"),
+            (
+                "ai éstrasse".to_owned(),
+                vec![0, 1, 2, 3, 5, 6, 7, 8, 9, 9, 10]
+            )
+        );
+        // 一个字符展开为多个
+        assert_eq!(normalize(&normalizer, "㈱").0, "(株)");
+
+        let normalizer = UnicodeNormalizer {
+            width: true,
+            traditional_to_simplified: true,
+            ..Default::default()
+        };
+        assert_eq!(normalize(&normalizer, "研究生選課１").0, "研究生选课1");
+
+        // 转换改变字数时逐字转换，位置仍对应原文
+        let mut chars = "選課㈱"
+            .chars()
+            .enumerate()
+            .map(|(i, c)| (c, i))
+            .collect::<Vec<_>>();
+        simplify(&mut chars, |s| fast2s::convert(s).replace('㈱', "(株)"));
+        assert_eq!(chars, vec![('选', 0), ('课', 1), ('㈱', 2)]);
+
+        // 索引和搜索使用同一个分析器，位置对应原文
+        let mut config = AnalyzerConfig::default();
+        config.normalize.traditional_to_simplified = true;
+        let analyzer = AnalyzerChain::from_config(&config);
+        let tokens = analyzer.analyze("ＡＩ講座");
+        let terms = tokens
+            .iter()
+            .map(|t| (t.term.as_str(), t.start, t.end))
+            .collect::<Vec<_>>();
+        assert_eq!(terms, vec![("ai", 0, 2), ("讲座", 2, 4)]);
+        assert_eq!(analyzer.normalize("ＡＩ").as_deref(), Some("ai"));
+    }
+}
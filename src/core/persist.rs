@@ -0,0 +1,226 @@
+use crate::{core::Core, dataset::Dataset};
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+/// 索引文件标识
+const MAGIC: &[u8; 8] = b"SZUIRIDX";
+
+/// 索引文件格式版本，索引的结构变化时递增
+pub const INDEX_VERSION: u32 = 17;
+
+/// 索引文件头
+///
+/// `magic(8) | version(u32) | dataset_fingerprint(u32) | config_fingerprint(u32) | checksum(u32) | len(u64)`，
+/// 之后是 bincode 编码的 [`IndexPayload`]，`checksum` 为其 CRC32
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexHeader {
+    pub version: u32,
+    /// 数据集指纹，见 [`Dataset::fingerprint`]
+    pub dataset_fingerprint: u32,
+    /// 配置指纹，见 [`crate::config::Config::fingerprint`]
+    pub config_fingerprint: u32,
+    pub checksum: u32,
+    pub len: u64,
+}
+
+impl IndexHeader {
+    const SIZE: usize = 8 + 4 * 4 + 8;
+
+    fn write(&self, w: &mut impl Write) -> anyhow::Result<()> {
+        w.write_all(MAGIC)?;
+        w.write_all(&self.version.to_le_bytes())?;
+        w.write_all(&self.dataset_fingerprint.to_le_bytes())?;
+        w.write_all(&self.config_fingerprint.to_le_bytes())?;
+        w.write_all(&self.checksum.to_le_bytes())?;
+        w.write_all(&self.len.to_le_bytes())?;
+        Ok(())
+    }
+
+    fn read(r: &mut impl Read) -> anyhow::Result<Self> {
+        let mut buf = [0; Self::SIZE];
+        r.read_exact(&mut buf).context("索引文件头不完整")?;
+        if &buf[0..8] != MAGIC {
+            bail!("不是索引文件");
+        }
+        let u32_at = |i: usize| u32::from_le_bytes(buf[i..i + 4].try_into().unwrap());
+        Ok(Self {
+            version: u32_at(8),
+            dataset_fingerprint: u32_at(12),
+            config_fingerprint: u32_at(16),
+            checksum: u32_at(20),
+            len: u64::from_le_bytes(buf[24..32].try_into().unwrap()),
+        })
+    }
+}
+
+/// 索引文件内容：文档和索引
+#[derive(Deserialize, Serialize)]
+struct IndexPayload<D, C> {
+    dataset: D,
+    core: C,
+}
+
+impl Core {
+    /// 保存索引（先写临时文件再重命名，避免留下不完整的文件）
+    pub fn save(
+        &self,
+        dataset: &Dataset,
+        dataset_fingerprint: u32,
+        config_fingerprint: u32,
+        path: impl AsRef<Path>,
+    ) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        tracing::info!("[Core] 开始保存索引 {:?}", path);
+        let start_time = std::time::Instant::now();
+
+        let payload = bincode::serialize(&IndexPayload {
+            dataset,
+            core: self,
+        })?;
+        let header = IndexHeader {
+            version: INDEX_VERSION,
+            dataset_fingerprint,
+            config_fingerprint,
+            checksum: crc32fast::hash(&payload),
+            len: payload.len() as u64,
+        };
+
+        let tmp_path = path.with_extension("tmp");
+        {
+            let mut w = BufWriter::new(File::create(&tmp_path)?);
+            header.write(&mut w)?;
+            w.write_all(&payload)?;
+            w.flush()?;
+        }
+        std::fs::rename(&tmp_path, path)?;
+
+        tracing::info!(
+            "[Core] 保存索引完成，用时 {:?}，大小 {} 字节",
+            start_time.elapsed(),
+            IndexHeader::SIZE + payload.len()
+        );
+        Ok(())
+    }
+
+    /// 读取索引，文件不存在、版本或指纹不一致（过期）时返回 `None`
+    pub fn load(
+        dataset_fingerprint: u32,
+        config_fingerprint: u32,
+        path: impl AsRef<Path>,
+    ) -> anyhow::Result<Option<(Dataset, Core)>> {
+        let path = path.as_ref();
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                tracing::info!("[Core] 索引文件 {:?} 不存在", path);
+                return Ok(None);
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        tracing::info!("[Core] 开始读取索引 {:?}", path);
+        let start_time = std::time::Instant::now();
+
+        let file_len = file.metadata()?.len();
+        let mut r = BufReader::new(file);
+        let header = IndexHeader::read(&mut r)?;
+        if header.version != INDEX_VERSION {
+            tracing::info!(
+                "[Core] 索引版本 {} 与当前版本 {} 不一致",
+                header.version,
+                INDEX_VERSION
+            );
+            return Ok(None);
+        }
+        if header.dataset_fingerprint != dataset_fingerprint {
+            tracing::info!("[Core] 数据集已变化");
+            return Ok(None);
+        }
+        if header.config_fingerprint != config_fingerprint {
+            tracing::info!("[Core] 配置已变化");
+            return Ok(None);
+        }
+
+        // 先按文件大小检查长度，再按长度分配内存，避免损坏的文件头导致过大的分配
+        if header.len.checked_add(IndexHeader::SIZE as u64) != Some(file_len) {
+            bail!("索引文件长度与文件头不一致");
+        }
+        let mut payload = Vec::with_capacity(header.len as usize);
+        r.read_to_end(&mut payload)?;
+        if payload.len() as u64 != header.len || crc32fast::hash(&payload) != header.checksum {
+            bail!("索引文件校验失败");
+        }
+        let IndexPayload::<Dataset, Core> { dataset, core } = bincode::deserialize(&payload)?;
+
+        tracing::info!(
+            "[Core] 读取索引完成，用时 {:?}，{} 个文档",
+            start_time.elapsed(),
+            dataset.docs.len()
+        );
+        Ok(Some((dataset, core)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, core::Query, dataset::Doc};
+
+    #[test]
+    fn test_save_load() {
+        let dataset = Dataset {
+            docs: vec![
+                Doc::builder()
+                    .with_title("研究生招生")
+                    .with_text("考试通知"),
+                Doc::builder().with_title("选课").with_text("本学期选课"),
+            ],
+        };
+        let core = Core::new(&dataset, &Config::default()).unwrap();
+
+        let path = std::env::temp_dir().join(format!("szu-ir-test-{}.bin", std::process::id()));
+        core.save(&dataset, 1, 2, &path).unwrap();
+
+        // 指纹不一致时视为过期
+        assert!(Core::load(0, 2, &path).unwrap().is_none());
+        assert!(Core::load(1, 0, &path).unwrap().is_none());
+
+        let (loaded_dataset, loaded) = Core::load(1, 2, &path).unwrap().unwrap();
+        assert_eq!(loaded_dataset.docs.len(), 2);
+        assert_eq!(
+            loaded.count_vectorizer.vocab().len(),
+            core.count_vectorizer.vocab().len()
+        );
+        let query = Query::parse("选课");
+        assert_eq!(
+            query.evaluate(&loaded, &loaded_dataset).unwrap().indices(),
+            query.evaluate(&core, &dataset).unwrap().indices()
+        );
+
+        // 内容损坏时校验失败
+        let mut bytes = std::fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(Core::load(1, 2, &path).is_err());
+
+        // 文件头中的长度损坏时，在分配内存之前报错
+        bytes[24..32].copy_from_slice(&u64::MAX.to_le_bytes());
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(Core::load(1, 2, &path).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+
+        // 只影响搜索的配置不计入指纹
+        let mut config = Config::default();
+        config.bm25f.boost.title = 5.0;
+        assert_eq!(config.fingerprint(), Config::default().fingerprint());
+        config.bm25f.k1 = 2.0;
+        assert_ne!(config.fingerprint(), Config::default().fingerprint());
+    }
+}
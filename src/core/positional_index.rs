@@ -0,0 +1,136 @@
+use crate::core::{full_words, Token, Vocabulary};
+use serde::{Deserialize, Serialize};
+
+/// 位置索引，只记录完整的词（不含搜索模式切出的子词），用于短语匹配
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct PositionalIndex {
+    /// 每个词项的倒排记录 (文档id, 位置列表)，按文档id递增, shape(nt,)
+    postings: Vec<Vec<(usize, Vec<u32>)>>,
+}
+
+impl PositionalIndex {
+    /// * `vocab`: 词汇表
+    /// * `analyzed`: 所有文档的分词结果, shape(nd,)
+    pub fn build(vocab: &Vocabulary, analyzed: &[Vec<Token>]) -> PositionalIndex {
+        tracing::info!("[PositionalIndex] 开始构建位置索引");
+        let start_time = std::time::Instant::now();
+
+        let mut index = PositionalIndex::default();
+        index.extend(vocab, 0, analyzed);
+
+        tracing::info!(
+            "[PositionalIndex] 构建位置索引完成，用时 {:?}",
+            start_time.elapsed()
+        );
+        index
+    }
+
+    /// 追加新文档
+    /// * `vocab`: 词汇表（可能新增了词项）
+    /// * `base`: 第一个新文档的id，要大于已有的文档id
+    /// * `analyzed`: 新文档的分词结果, shape(nd_new,)
+    pub fn extend(&mut self, vocab: &Vocabulary, base: usize, analyzed: &[Vec<Token>]) {
+        self.postings.resize(vocab.len(), vec![]);
+        for (i, tokens) in analyzed.iter().enumerate() {
+            let d = base + i;
+            for token in full_words(tokens) {
+                let Some(t) = vocab.id(&token.term) else {
+                    continue;
+                };
+                let position = token.position as u32;
+                match self.postings[t].last_mut() {
+                    Some((last, positions)) if *last == d => positions.push(position),
+                    _ => self.postings[t].push((d, vec![position])),
+                }
+            }
+        }
+    }
+
+    /// 合并多个位置索引，文档id重新编号
+    /// * `parts`: 每个位置索引，以及其中的文档id到新id的映射（`None` 表示丢弃），新id要按顺序递增
+    pub fn merge(parts: &[(&PositionalIndex, &[Option<usize>])]) -> PositionalIndex {
+        let nt = parts
+            .iter()
+            .map(|(p, _)| p.postings.len())
+            .max()
+            .unwrap_or(0);
+        let mut postings = vec![vec![]; nt];
+        for (part, remap) in parts {
+            for (t, posting) in part.postings.iter().enumerate() {
+                postings[t].extend(
+                    posting
+                        .iter()
+                        .filter_map(|(d, positions)| Some((remap[*d]?, positions.clone()))),
+                );
+            }
+        }
+        PositionalIndex { postings }
+    }
+
+    /// 词项t在文档d中出现的位置（递增）
+    pub fn positions(&self, t: usize, d: usize) -> &[u32] {
+        let Some(posting) = self.postings.get(t) else {
+            return &[];
+        };
+        match posting.binary_search_by_key(&d, |&(d, _)| d) {
+            Ok(i) => &posting[i].1,
+            Err(_) => &[],
+        }
+    }
+
+    /// 文档d中是否包含短语
+    /// * `phrase`: 短语中完整的词的 (词项id, 相对位置)
+    pub fn contains_phrase(&self, phrase: &[(usize, usize)], d: usize) -> bool {
+        let [(t0, p0), ref rest @ ..] = *phrase else {
+            return true;
+        };
+        // 只有一个词时不需要判断相邻，也可以是其他词的子词
+        if rest.is_empty() {
+            return true;
+        }
+
+        // 以第一个词的每个出现位置为起点，检查其余词是否出现在对应的位置
+        self.positions(t0, d).iter().any(|&start| {
+            let Some(start) = (start as usize).checked_sub(p0) else {
+                return false;
+            };
+            rest.iter().all(|&(t, p)| {
+                self.positions(t, d)
+                    .binary_search(&((start + p) as u32))
+                    .is_ok()
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::CountVectorizer;
+
+    #[test]
+    fn test_positional_index() {
+        let mut cv = CountVectorizer::new();
+        let x = vec!["研究生招生考试".to_owned(), "招生 研究生".to_owned()];
+        cv.fit(&x);
+        let analyzed = x.iter().map(|s| cv.analyze(s)).collect::<Vec<_>>();
+        let index = PositionalIndex::build(cv.vocab(), &analyzed);
+
+        let phrase = |s: &str| {
+            full_words(&cv.analyze(s))
+                .map(|t| (cv.vocab().id(&t.term).unwrap(), t.position))
+                .collect::<Vec<_>>()
+        };
+        assert!(index.contains_phrase(&phrase("研究生 招生"), 0));
+        assert!(!index.contains_phrase(&phrase("研究生 招生"), 1));
+        // 子词与所在的词位置相同，但不与后面的词相邻
+        assert!(!index.contains_phrase(&phrase("研究 招生"), 0));
+        let phrase = phrase("研究生 招生");
+
+        // 合并时重新编号，丢弃的文档不再出现
+        let merged =
+            PositionalIndex::merge(&[(&index, &[None, Some(0)]), (&index, &[Some(1), None])]);
+        assert!(!merged.contains_phrase(&phrase, 0));
+        assert!(merged.contains_phrase(&phrase, 1));
+    }
+}
@@ -0,0 +1,440 @@
+use crate::{
+    core::{cs_helper, Core, FacetField, Field},
+    dataset::Dataset,
+};
+use sprs::CsVec;
+
+/// 搜索范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// 在某个字段的文本中搜索
+    Field(Field),
+    /// 发布单位，精确匹配
+    User,
+    /// 类别，精确匹配
+    Infotype,
+}
+
+impl Scope {
+    pub fn from_name(name: &str) -> Option<Scope> {
+        match name {
+            "user" => Some(Scope::User),
+            "infotype" => Some(Scope::Infotype),
+            _ => Field::from_name(name).map(Scope::Field),
+        }
+    }
+
+    /// 限定的字段
+    pub fn field(self) -> Option<Field> {
+        match self {
+            Scope::Field(f) => Some(f),
+            _ => None,
+        }
+    }
+}
+
+/// 查询语法树
+///
+/// 语法示例: `title:奖学金 AND (申请 OR 评选) -讲座 user:教务部`
+/// * 空格分隔的词默认为 AND，`OR` 优先级低于 `AND`
+/// * `NOT x` 或 `-x` 排除
+/// * `"..."` 短语
+/// * `title:` / `text:` / `attachments:` 限定字段，`user:` / `infotype:` 精确匹配
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    /// 词，切分出的词项都要包含
+    Term(String),
+    /// 短语，词项要相邻
+    Phrase(String),
+    /// 限定搜索范围
+    Scoped(Scope, Box<Query>),
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+}
+
+/// 词法单元
+#[derive(Debug, Clone, PartialEq)]
+enum Lexeme {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Minus,
+    Scope(Scope),
+    Word(String),
+    Phrase(String),
+}
+
+fn lex(s: &str) -> Vec<Lexeme> {
+    let chars = s.chars().collect::<Vec<_>>();
+    let is_delimiter = |c: char| c.is_whitespace() || c == '(' || c == ')' || c == '"';
+
+    let mut lexemes = vec![];
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            c if c.is_whitespace() => i += 1,
+            '(' => {
+                lexemes.push(Lexeme::LParen);
+                i += 1;
+            }
+            ')' => {
+                lexemes.push(Lexeme::RParen);
+                i += 1;
+            }
+            '"' => {
+                // 没有配对的引号时，短语一直到结尾
+                let end = (i + 1..chars.len())
+                    .find(|&j| chars[j] == '"')
+                    .unwrap_or(chars.len());
+                lexemes.push(Lexeme::Phrase(chars[i + 1..end].iter().collect()));
+                i = end + 1;
+            }
+            '-' => {
+                lexemes.push(Lexeme::Minus);
+                i += 1;
+            }
+            _ => {
+                let end = (i..chars.len())
+                    .find(|&j| is_delimiter(chars[j]))
+                    .unwrap_or(chars.len());
+                let word = chars[i..end].iter().collect::<String>();
+                i = end;
+
+                match word.as_str() {
+                    "AND" => lexemes.push(Lexeme::And),
+                    "OR" => lexemes.push(Lexeme::Or),
+                    "NOT" => lexemes.push(Lexeme::Not),
+                    _ => match word.split_once(':') {
+                        Some((name, rest)) if Scope::from_name(name).is_some() => {
+                            lexemes.push(Lexeme::Scope(Scope::from_name(name).unwrap()));
+                            if !rest.is_empty() {
+                                lexemes.push(Lexeme::Word(rest.to_owned()));
+                            }
+                        }
+                        _ => lexemes.push(Lexeme::Word(word)),
+                    },
+                }
+            }
+        }
+    }
+    lexemes
+}
+
+/// 递归下降解析，遇到不合法的语法时尽量跳过而不报错
+struct Parser {
+    lexemes: Vec<Lexeme>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Lexeme> {
+        self.lexemes.get(self.pos)
+    }
+
+    /// or := and (OR and)*
+    fn parse_or(&mut self) -> Option<Query> {
+        let mut clauses = vec![];
+        loop {
+            clauses.extend(self.parse_and());
+            if self.peek() == Some(&Lexeme::Or) {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        combine(clauses, Query::Or)
+    }
+
+    /// and := unary ([AND] unary)*
+    fn parse_and(&mut self) -> Option<Query> {
+        let mut clauses = vec![];
+        loop {
+            match self.peek() {
+                None | Some(Lexeme::RParen) | Some(Lexeme::Or) => break,
+                Some(Lexeme::And) => self.pos += 1,
+                _ => clauses.extend(self.parse_unary()),
+            }
+        }
+        combine(clauses, Query::And)
+    }
+
+    /// unary := (NOT | -) unary | primary
+    fn parse_unary(&mut self) -> Option<Query> {
+        match self.peek() {
+            Some(Lexeme::Not) | Some(Lexeme::Minus) => {
+                self.pos += 1;
+                self.parse_unary().map(|q| Query::Not(Box::new(q)))
+            }
+            _ => self.parse_primary(),
+        }
+    }
+
+    /// primary := ( or ) | scope: unary | word | "phrase"
+    fn parse_primary(&mut self) -> Option<Query> {
+        match self.peek()? {
+            Lexeme::RParen | Lexeme::Or | Lexeme::And => return None,
+            _ => {}
+        }
+
+        let lexeme = self.lexemes[self.pos].clone();
+        self.pos += 1;
+        match lexeme {
+            Lexeme::LParen => {
+                let query = self.parse_or();
+                if self.peek() == Some(&Lexeme::RParen) {
+                    self.pos += 1;
+                }
+                query
+            }
+            Lexeme::Scope(scope) => self
+                .parse_unary()
+                .map(|q| Query::Scoped(scope, Box::new(q))),
+            Lexeme::Word(word) => Some(Query::Term(word)),
+            Lexeme::Phrase(phrase) if !phrase.trim().is_empty() => Some(Query::Phrase(phrase)),
+            _ => None,
+        }
+    }
+}
+
+fn combine(mut clauses: Vec<Query>, f: fn(Vec<Query>) -> Query) -> Option<Query> {
+    match clauses.len() {
+        0 => None,
+        1 => clauses.pop(),
+        _ => Some(f(clauses)),
+    }
+}
+
+impl Query {
+    pub fn parse(s: &str) -> Query {
+        let mut parser = Parser {
+            lexemes: lex(s),
+            pos: 0,
+        };
+
+        let mut clauses = vec![];
+        while parser.pos < parser.lexemes.len() {
+            clauses.extend(parser.parse_or());
+            // 跳过多余的右括号
+            if parser.peek() == Some(&Lexeme::RParen) {
+                parser.pos += 1;
+            }
+        }
+        combine(clauses, Query::And).unwrap_or(Query::And(vec![]))
+    }
+
+    /// 只由词和短语组成（AND 连接）时，返回其中的短语
+    pub fn as_plain(&self) -> Option<Vec<&str>> {
+        match self {
+            Query::Term(_) => Some(vec![]),
+            Query::Phrase(phrase) => Some(vec![phrase]),
+            Query::And(clauses) => clauses.iter().try_fold(vec![], |mut phrases, q| {
+                match q {
+                    Query::Term(_) => {}
+                    Query::Phrase(phrase) => phrases.push(phrase.as_str()),
+                    _ => return None,
+                }
+                Some(phrases)
+            }),
+            _ => None,
+        }
+    }
+
+    /// 用于计算得分的词（不在 NOT 中，也不是精确匹配的元数据）
+    pub fn positive_text(&self) -> Vec<&str> {
+        match self {
+            Query::Term(s) | Query::Phrase(s) => vec![s],
+            Query::Scoped(Scope::Field(_), q) => q.positive_text(),
+            Query::Scoped(_, _) | Query::Not(_) => vec![],
+            Query::And(clauses) | Query::Or(clauses) => {
+                clauses.iter().flat_map(|q| q.positive_text()).collect()
+            }
+        }
+    }
+
+    /// * `returns`: 匹配的文档, shape(nd,)；
+    ///   没有可以搜索的词项（如只有停用词、标点）时返回 `None`
+    pub fn evaluate(&self, core: &Core, dataset: &Dataset) -> Option<CsVec<bool>> {
+        self.eval(core, dataset, None)
+    }
+
+    /// 分析后没有词项的词和短语不作为条件：AND 中跳过，OR、NOT 中忽略
+    fn eval(&self, core: &Core, dataset: &Dataset, scope: Option<Scope>) -> Option<CsVec<bool>> {
+        let nd = dataset.docs.len();
+        let result = match self {
+            Query::Term(s) | Query::Phrase(s)
+                if matches!(scope, Some(Scope::User | Scope::Infotype)) =>
+            {
+                meta_match(core, scope.unwrap(), s)
+            }
+            Query::Term(term) => {
+                let x = core.count_vectorizer.transform(std::slice::from_ref(term));
+                let x = x.outer_view(0).unwrap();
+                // 不在词汇表中的词不匹配任何文档，只有停用词、标点时不作为条件
+                if x.nnz() == 0 && core.count_vectorizer.analyze(term).is_empty() {
+                    return None;
+                }
+                core.search(x, scope.and_then(Scope::field), None)
+            }
+            Query::Phrase(phrase) => {
+                let ids = match core.phrase(phrase) {
+                    Some(ids) if ids.is_empty() => return None,
+                    Some(ids) => ids,
+                    None => return Some(CsVec::new(nd, vec![], vec![])), // 短语中有词不在词汇表中
+                };
+                let x = core
+                    .count_vectorizer
+                    .transform(std::slice::from_ref(phrase));
+                let x = x.outer_view(0).unwrap();
+                let field = scope.and_then(Scope::field);
+                let docs = core
+                    .search(x, field, None)
+                    .indices()
+                    .iter()
+                    .copied()
+                    .filter(|&d| core.contains_phrase(&ids, d, field))
+                    .collect::<Vec<_>>();
+                let len = docs.len();
+                CsVec::new(nd, docs, vec![true; len])
+            }
+            Query::Scoped(scope, q) => return q.eval(core, dataset, Some(*scope)),
+            Query::And(clauses) => {
+                let (negative, positive): (Vec<_>, Vec<_>) =
+                    clauses.iter().partition(|q| matches!(q, Query::Not(_)));
+                let positive = positive
+                    .iter()
+                    .filter_map(|q| q.eval(core, dataset, scope))
+                    .collect::<Vec<_>>();
+                let negative = negative
+                    .iter()
+                    .filter_map(|q| {
+                        let Query::Not(q) = q else { unreachable!() };
+                        q.eval(core, dataset, scope)
+                    })
+                    .collect::<Vec<_>>();
+                if positive.is_empty() && negative.is_empty() {
+                    return None;
+                }
+
+                // 先求交集，再排除
+                let mut result = positive
+                    .into_iter()
+                    .reduce(|acc, v| cs_helper::intersection(acc.view(), v.view()))
+                    .unwrap_or_else(|| universe(nd));
+                for v in negative {
+                    result = cs_helper::difference(result.view(), v.view());
+                }
+                result
+            }
+            Query::Or(clauses) => clauses
+                .iter()
+                .filter_map(|q| q.eval(core, dataset, scope))
+                .reduce(|acc, v| cs_helper::union(acc.view(), v.view()))?,
+            Query::Not(q) => {
+                let v = q.eval(core, dataset, scope)?;
+                cs_helper::difference(universe(nd).view(), v.view())
+            }
+        };
+        Some(result)
+    }
+}
+
+/// 所有文档
+fn universe(nd: usize) -> CsVec<bool> {
+    CsVec::new(nd, (0..nd).collect(), vec![true; nd])
+}
+
+/// 元数据精确匹配的文档
+fn meta_match(core: &Core, scope: Scope, value: &str) -> CsVec<bool> {
+    let field = match scope {
+        Scope::User => FacetField::User,
+        Scope::Infotype => FacetField::Infotype,
+        Scope::Field(_) => return CsVec::new(core.num_docs, vec![], vec![]),
+    };
+    core.filter_index.docs(field, &[value.to_owned()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn term(s: &str) -> Query {
+        Query::Term(s.to_owned())
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Query::parse("研究生 招生"),
+            Query::And(vec![term("研究生"), term("招生")])
+        );
+        assert_eq!(
+            Query::parse("title:奖学金 AND (申请 OR 评选) -讲座 user:教务部"),
+            Query::And(vec![
+                Query::Scoped(Scope::Field(Field::Title), Box::new(term("奖学金"))),
+                Query::Or(vec![term("申请"), term("评选")]),
+                Query::Not(Box::new(term("讲座"))),
+                Query::Scoped(Scope::User, Box::new(term("教务部"))),
+            ])
+        );
+        assert_eq!(
+            Query::parse("a b OR NOT c"),
+            Query::Or(vec![
+                Query::And(vec![term("a"), term("b")]),
+                Query::Not(Box::new(term("c"))),
+            ])
+        );
+        assert_eq!(
+            Query::parse("title:\"研究生 招生\" 2023-2024"),
+            Query::And(vec![
+                Query::Scoped(
+                    Scope::Field(Field::Title),
+                    Box::new(Query::Phrase("研究生 招生".to_owned()))
+                ),
+                term("2023-2024"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_lenient() {
+        assert_eq!(Query::parse(""), Query::And(vec![]));
+        assert_eq!(Query::parse("a OR"), term("a"));
+        assert_eq!(Query::parse("(a b"), Query::And(vec![term("a"), term("b")]));
+        assert_eq!(Query::parse("a) b"), Query::And(vec![term("a"), term("b")]));
+        assert_eq!(Query::parse("http://x"), term("http://x"));
+    }
+
+    #[test]
+    fn test_as_plain() {
+        assert_eq!(Query::parse("a \"b c\"").as_plain(), Some(vec!["b c"]));
+        assert_eq!(Query::parse("a OR b").as_plain(), None);
+        assert_eq!(
+            Query::parse("title:a (b OR c) -d user:e").positive_text(),
+            vec!["a", "b", "c"]
+        );
+    }
+
+    #[test]
+    fn test_stopwords() {
+        let dataset = Dataset::sample();
+        let core = Core::new(&dataset, &Config::default()).unwrap();
+        let evaluate = |s: &str| {
+            Query::parse(s)
+                .evaluate(&core, &dataset)
+                .map(|v| v.indices().to_vec())
+        };
+
+        // 只有停用词、标点的子句不作为条件
+        assert_eq!(evaluate("招生 的 -讲座"), Some(vec![0]));
+        assert_eq!(evaluate("招生 -的"), Some(vec![0]));
+        assert_eq!(evaluate("(的 OR 讲座) \"the\""), Some(vec![3]));
+        assert_eq!(evaluate("的 -讲座"), Some(vec![0, 1, 2]));
+        assert_eq!(evaluate("的 OR ，"), None);
+        // 不在词汇表中的词仍然不匹配任何文档
+        assert_eq!(evaluate("招生 量子"), Some(vec![]));
+    }
+}
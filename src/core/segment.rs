@@ -0,0 +1,358 @@
+use crate::core::{
+    compact_ids, cs_helper, Field, FieldIndex, IndexStats, InvertedIndex, PerField, PositionalIndex,
+};
+use serde::{Deserialize, Serialize};
+use sprs::{CsMat, CsVec, CsVecView};
+use std::collections::HashSet;
+
+/// 索引段，创建后不再修改
+///
+/// 段内的文档按全局id递增排列，词频矩阵的行号为段内id；
+/// 词汇表由所有段共享，早创建的段的词频矩阵列数可能小于词汇表大小
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Segment {
+    /// 段内文档的全局id（递增）, shape(nd,)
+    doc_ids: Vec<usize>,
+    /// 每个字段的词频、倒排索引和位置索引
+    pub fields: PerField<FieldIndex>,
+    /// 所有字段的倒排索引（词频为所有字段之和）
+    pub index: InvertedIndex,
+    /// 段内文档的统计量
+    pub stats: IndexStats,
+    /// 每个文档被停用词过滤去掉的倒排记录数, shape(nd,)
+    removed_postings: Vec<usize>,
+}
+
+impl Segment {
+    /// * `doc_ids`: 段内文档的全局id（递增）, shape(nd,)
+    /// * `field_data`: 每个字段的词频, shape(nd,nt)
+    /// * `positions`: 每个字段的位置索引（段内id）
+    /// * `removed_postings`: 每个文档被停用词过滤去掉的倒排记录数, shape(nd,)
+    pub fn new(
+        doc_ids: Vec<usize>,
+        field_data: PerField<CsMat<usize>>,
+        positions: PerField<PositionalIndex>,
+        removed_postings: Vec<usize>,
+    ) -> Segment {
+        // 所有字段的词频之和只用于构建倒排索引，不保存
+        let data = field_data
+            .iter()
+            .skip(1)
+            .fold(field_data.title.clone(), |acc, (_, x)| &acc + x);
+        let index = InvertedIndex::build(data.view());
+        drop(data);
+        let stats = IndexStats {
+            removed_postings: removed_postings.iter().sum(),
+            ..IndexStats::build(&field_data.map(|_, x| x.view()))
+        };
+
+        let mut field_data = field_data;
+        let mut positions = positions;
+        let fields = PerField::from_fn(|field| {
+            let data = std::mem::replace(&mut field_data[field], CsMat::zero((0, 0)));
+            FieldIndex {
+                index: InvertedIndex::build(data.view()),
+                data,
+                positions: std::mem::take(&mut positions[field]),
+            }
+        });
+
+        Segment {
+            doc_ids,
+            fields,
+            index,
+            stats,
+            removed_postings,
+        }
+    }
+
+    /// 合并相邻的段，去掉已删除的文档
+    /// * `segments`: 按文档id递增排列的段
+    /// * `deleted`: 已删除的文档
+    /// * `returns`: 合并后的段，所有文档都已删除时返回 `None`
+    pub fn merge(segments: &[&Segment], deleted: &HashSet<usize>) -> Option<Segment> {
+        tracing::info!("[Segment] 开始合并 {} 个段", segments.len());
+        let start_time = std::time::Instant::now();
+
+        // 每个段保留的文档（段内id），以及段内id到新id的映射
+        let mut doc_ids = vec![];
+        let mut kept = vec![];
+        let mut remaps = vec![];
+        for segment in segments {
+            let mut rows = vec![];
+            let mut remap = vec![None; segment.len()];
+            for (i, &d) in segment.doc_ids.iter().enumerate() {
+                if !deleted.contains(&d) {
+                    remap[i] = Some(doc_ids.len());
+                    rows.push(i);
+                    doc_ids.push(d);
+                }
+            }
+            kept.push(rows);
+            remaps.push(remap);
+        }
+        if doc_ids.is_empty() {
+            return None;
+        }
+
+        let field_data = PerField::from_fn(|field| {
+            segments
+                .iter()
+                .zip(&kept)
+                .map(|(segment, rows)| {
+                    cs_helper::cs_mat_select_rows(segment.fields[field].data.view(), rows)
+                })
+                .reduce(|acc, x| cs_helper::cs_mat_vstack(acc.view(), x.view()))
+                .unwrap()
+        });
+        let positions = PerField::from_fn(|field| {
+            let parts = segments
+                .iter()
+                .zip(&remaps)
+                .map(|(segment, remap)| (&segment.fields[field].positions, remap.as_slice()))
+                .collect::<Vec<_>>();
+            PositionalIndex::merge(&parts)
+        });
+        let removed_postings = segments
+            .iter()
+            .zip(&kept)
+            .flat_map(|(segment, rows)| rows.iter().map(|&i| segment.removed_postings[i]))
+            .collect();
+        let segment = Segment::new(doc_ids, field_data, positions, removed_postings);
+
+        tracing::info!(
+            "[Segment] 合并完成，用时 {:?}，{} 个文档",
+            start_time.elapsed(),
+            segment.len()
+        );
+        Some(segment)
+    }
+
+    /// 段内文档数（含已删除的文档）
+    pub fn len(&self) -> usize {
+        self.doc_ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.doc_ids.is_empty()
+    }
+
+    /// 段内文档的全局id（递增）
+    pub fn doc_ids(&self) -> &[usize] {
+        &self.doc_ids
+    }
+
+    /// 合并段清除文档后，前移段内文档的全局id（段内id不变）
+    /// * `purged`: 清除的文档（递增）
+    pub fn compact_ids(&mut self, purged: &[usize]) {
+        compact_ids(&mut self.doc_ids, purged);
+    }
+
+    /// 段内已删除的文档数
+    pub fn num_deleted(&self, deleted: &HashSet<usize>) -> usize {
+        self.doc_ids.iter().filter(|d| deleted.contains(d)).count()
+    }
+
+    /// 全局id转为段内id
+    pub fn local_id(&self, d: usize) -> Option<usize> {
+        self.doc_ids.binary_search(&d).ok()
+    }
+
+    /// 段内文档i的词频（所有字段之和，由每个字段的词频相加）, shape(nt,)
+    pub fn row(&self, i: usize) -> CsVec<usize> {
+        let rows = self.field_rows(i);
+        rows.iter()
+            .skip(1)
+            .fold(rows.title.to_owned(), |acc, (_, x)| &acc + x)
+    }
+
+    /// 段内文档i每个字段的词频, shape(nt,)
+    pub fn field_rows(&self, i: usize) -> PerField<CsVecView<'_, usize>> {
+        self.fields.map(|_, f| f.data.outer_view(i).unwrap())
+    }
+
+    /// 段内文档i被停用词过滤去掉的倒排记录数
+    pub fn removed_postings(&self, i: usize) -> usize {
+        self.removed_postings[i]
+    }
+
+    /// 段内文档i每个字段的长度（词数）
+    pub fn field_len(&self, i: usize) -> PerField<f64> {
+        self.field_rows(i)
+            .map(|_, x| x.data().iter().sum::<usize>() as f64)
+    }
+
+    /// 检索，返回全局id
+    /// * `x`: 搜索词词频, shape(nt,)
+    /// * `field`: 限定字段，`None` 表示所有字段
+    /// * `min_match`: 至少匹配的词项数，`None` 表示全部匹配
+    pub fn search(
+        &self,
+        x: CsVecView<usize>,
+        field: Option<Field>,
+        min_match: Option<usize>,
+    ) -> Vec<usize> {
+        let index = match field {
+            Some(f) => &self.fields[f].index,
+            None => &self.index,
+        };
+        let result: CsVec<bool> = match min_match {
+            Some(min_match) => index.search_min_match(x, min_match),
+            None => index.search(x),
+        };
+        result.indices().iter().map(|&i| self.doc_ids[i]).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Config,
+        core::{Core, FacetField},
+        dataset::{Dataset, Doc},
+    };
+
+    /// 所有文档的 BM25 和 TF-IDF 得分
+    fn scores(core: &Core, keyword: &str) -> Vec<(usize, f64, f64)> {
+        let q = core.count_vectorizer.transform(&[keyword.to_owned()]);
+        let q = q.outer_view(0).unwrap();
+        let q_tfidf = core.tfidf_vectorizer.transform(
+            core.count_vectorizer
+                .transform(&[keyword.to_owned()])
+                .view(),
+        );
+        let docs = core.search(q, None, Some(1));
+        let freqs = core.term_freqs(q.indices(), docs.indices());
+        let mut scores = docs
+            .indices()
+            .iter()
+            .zip(&freqs)
+            .map(|(&d, matched)| {
+                let (segment, i) = core.locate(d).unwrap();
+                // 使用倒排记录中的词频和预先计算的词数、模长，与即时计算文档向量的得分相同
+                let tfidf =
+                    core.tfidf_vectorizer
+                        .score_doc(q_tfidf.outer_view(0).unwrap(), d, matched);
+                let expected = core
+                    .tfidf_vectorizer
+                    .score(q_tfidf.outer_view(0).unwrap(), segment.row(i).view());
+                assert!((tfidf - expected).abs() < 1e-9);
+                (
+                    d,
+                    core.bm25_scorer.score(
+                        q,
+                        segment.row(i).view(),
+                        segment.field_len(i).iter().map(|(_, &l)| l).sum(),
+                    ),
+                    tfidf,
+                )
+            })
+            .collect::<Vec<_>>();
+        scores.sort_by_key(|&(d, _, _)| d);
+        scores
+    }
+
+    fn assert_same_scores(a: &[(usize, f64, f64)], b: &[(usize, f64, f64)]) {
+        assert_eq!(a.len(), b.len());
+        for (a, b) in a.iter().zip(b) {
+            assert_eq!(a.0, b.0);
+            assert!((a.1 - b.1).abs() < 1e-9);
+            assert!((a.2 - b.2).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_segments() {
+        let config = Config::default();
+        let all = Dataset::sample().docs;
+
+        // 一次性构建（一个段）
+        let monolithic = Core::new(&Dataset { docs: all.clone() }, &config).unwrap();
+        assert_eq!(monolithic.segments.len(), 1);
+
+        // 逐个添加（多个段），全局统计量与一次性构建的相同
+        let mut dataset = Dataset { docs: vec![] };
+        let mut core = Core::new(&dataset, &config).unwrap();
+        for doc in all.clone() {
+            core.update(&mut dataset, vec![doc], &[]);
+        }
+        assert_eq!(core.segments.len(), 4);
+        assert_eq!(core.stats.n_docs, monolithic.stats.n_docs);
+        assert_eq!(core.stats.field_len, monolithic.stats.field_len);
+        for keyword in ["研究生", "研究生 选课 讲座", "奖学金评选"] {
+            assert_same_scores(&scores(&core, keyword), &scores(&monolithic, keyword));
+        }
+
+        // 合并后得分不变，已删除的文档被清除，其后的文档id前移
+        core.update(&mut dataset, vec![], &[1, 3]);
+        let before = scores(&core, "研究生 选课 讲座");
+        let sources = core.segments[0..2].to_vec();
+        let refs = sources.iter().map(|s| s.as_ref()).collect::<Vec<_>>();
+        let merged = Segment::merge(&refs, &core.deleted).unwrap();
+        assert_eq!(merged.doc_ids(), &[0]);
+        assert!(core.replace_segments(&mut dataset, 0..2, &sources, Some(merged)));
+        assert_eq!(core.segments.len(), 3);
+        assert_eq!(core.num_docs, 3);
+        let urls = |dataset: &Dataset| {
+            dataset
+                .docs
+                .iter()
+                .map(|doc| doc.url.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(urls(&dataset), vec!["1", "3", "4"]);
+        // 清除的文档不再保留墓碑，其余的墓碑使用新的id
+        assert_eq!(core.deleted, HashSet::from([2]));
+        let before = before
+            .into_iter()
+            .map(|(d, s1, s2)| (if d > 1 { d - 1 } else { d }, s1, s2))
+            .collect::<Vec<_>>();
+        assert_same_scores(&scores(&core, "研究生 选课 讲座"), &before);
+        assert!(core
+            .search(
+                core.count_vectorizer
+                    .transform(&["选课".to_owned()])
+                    .outer_view(0)
+                    .unwrap(),
+                None,
+                None
+            )
+            .indices()
+            .is_empty());
+
+        // 删除的文档都被清除后，与不含它们的一次性构建相同
+        let sources = core.segments.clone();
+        let refs = sources.iter().map(|s| s.as_ref()).collect::<Vec<_>>();
+        let merged = Segment::merge(&refs, &core.deleted);
+        assert!(core.replace_segments(&mut dataset, 0..3, &sources, merged));
+        assert_eq!(urls(&dataset), vec!["1", "3"]);
+        assert!(core.deleted.is_empty());
+        let rebuilt = Core::new(
+            &Dataset {
+                docs: vec![all[0].clone(), all[2].clone()],
+            },
+            &config,
+        )
+        .unwrap();
+        assert_eq!(core.stats.n_docs, rebuilt.stats.n_docs);
+        assert_same_scores(
+            &scores(&core, "研究生 讲座"),
+            &scores(&rebuilt, "研究生 讲座"),
+        );
+        assert_eq!(
+            core.filter_index
+                .docs(FacetField::User, &["教务部".to_owned()])
+                .indices(),
+            &[0, 1]
+        );
+
+        // 新文档的id接在压缩后的文档之后
+        core.upsert(
+            &mut dataset,
+            vec![Doc::builder().with_url("4").with_title("讲座")],
+        );
+        assert_eq!(urls(&dataset), vec!["1", "3", "4"]);
+        assert_eq!(core.complete_titles("讲", 10)[0].count, 1);
+    }
+}
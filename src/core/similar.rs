@@ -0,0 +1,88 @@
+use crate::core::Core;
+use sprs::CsVec;
+
+impl Core {
+    /// 相似文档（More Like This）：取文档d的 TF-IDF 向量中权重最高的若干词项作为查询，
+    /// 用 top-k 检索包含任意一个词项的文档中与查询的余弦相似度最高的k个（得分相同时id小的在前）
+    /// * `max_query_terms`: 查询最多包含的词项数
+    /// * `k`: 返回的文档数
+    /// * `accept`: 文档是否可以作为结果（满足过滤条件等）
+    /// * `returns`: 除d以外未删除的文档及得分，按得分递减排列；d不存在或已删除时返回 `None`
+    pub fn similar(
+        &self,
+        d: usize,
+        max_query_terms: usize,
+        k: usize,
+        accept: impl Fn(usize) -> bool,
+    ) -> Option<Vec<(usize, f64)>> {
+        if self.is_deleted(d) {
+            return None;
+        }
+        let (segment, i) = self.locate(d)?;
+        let row = segment.row(i);
+
+        // 权重最高的词项（忽略出现在所有文档中、IDF 为 0 的词项）
+        let tf_idf = self.tfidf_vectorizer.transform_row(row.view());
+        let mut terms = tf_idf
+            .iter()
+            .filter(|&(_, &w)| w > 0.0)
+            .map(|(t, &w)| (t, w))
+            .collect::<Vec<_>>();
+        terms.sort_by(|(t1, w1), (t2, w2)| w2.partial_cmp(w1).unwrap().then(t1.cmp(t2)));
+        terms.truncate(max_query_terms);
+        terms.sort_by_key(|&(t, _)| t);
+        tracing::info!(
+            "[Core] 文档 {} 的相似文档查询包含 {} 个词项",
+            d,
+            terms.len()
+        );
+
+        let (indices, data): (Vec<_>, Vec<_>) = terms.into_iter().unzip();
+        let q_tf_idf = CsVec::new(row.dim(), indices, data);
+
+        // 每个词项贡献的上界，跳过不可能进入前k名的文档
+        let bounds = self.max_scores.tfidf(q_tf_idf.view());
+        let accept = |other| other != d && !self.is_deleted(other) && accept(other);
+        let score = |other, matched: &[(usize, usize)]| {
+            Some(
+                self.tfidf_vectorizer
+                    .score_doc(q_tf_idf.view(), other, matched),
+            )
+        };
+        let d_score = self.top_k(&bounds, 1, k, accept, score).hits;
+        Some(d_score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{config::Config, core::Core, dataset::Dataset};
+
+    #[test]
+    fn test_similar() {
+        let mut dataset = Dataset::sample();
+        let mut core = Core::new(&dataset, &Config::default()).unwrap();
+
+        // 不包含文档自身，没有共同词项的文档不出现
+        let similar = core.similar(0, 25, 10, |_| true).unwrap();
+        assert_eq!(similar[0].0, 2);
+        assert!(similar.iter().all(|&(d, _)| d != 0 && d != 3));
+        // 只返回前k个满足条件的文档
+        assert!(core.similar(0, 25, 0, |_| true).unwrap().is_empty());
+        assert!(core
+            .similar(0, 25, 10, |d| d != 2)
+            .unwrap()
+            .iter()
+            .all(|&(d, _)| d != 2));
+
+        // 已删除的文档不出现，也不能作为查询
+        core.update(&mut dataset, vec![], &[2]);
+        assert!(core
+            .similar(0, 25, 10, |_| true)
+            .unwrap()
+            .iter()
+            .all(|&(d, _)| d != 2));
+        assert!(core.similar(2, 25, 10, |_| true).is_none());
+        assert!(core.similar(10, 25, 10, |_| true).is_none());
+    }
+}
@@ -0,0 +1,241 @@
+use crate::core::{full_words, Core, Token};
+use pinyin::ToPinyin;
+use serde::{Deserialize, Serialize};
+
+/// 拼写纠错建议
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Correction {
+    /// 搜索词中被替换的部分
+    pub original: String,
+    /// 替换为的词项
+    pub suggestion: String,
+    /// 编辑距离（字符）
+    pub distance: usize,
+    /// 拼音的编辑距离（模糊音视为相同），不是中文时为 `None`
+    pub pinyin_distance: Option<usize>,
+    /// 建议的词项的文档频率
+    pub df: usize,
+}
+
+impl Correction {
+    /// 越小越好：拼音距离、编辑距离、原词越长（上下文越多）、文档频率越高
+    fn rank(&self) -> impl Ord + '_ {
+        (
+            self.pinyin_distance.unwrap_or(self.distance),
+            self.distance,
+            std::cmp::Reverse(self.original.chars().count()),
+            std::cmp::Reverse(self.df),
+            &self.suggestion,
+        )
+    }
+}
+
+impl Core {
+    /// 词项t是否在索引中（在词汇表中，且出现在未删除的文档中）
+    pub fn contains_term(&self, term: &str) -> bool {
+        self.count_vectorizer
+            .vocab()
+            .id(term)
+            .is_some_and(|t| self.stats.df(t) > 0)
+    }
+
+    /// 搜索词中不在索引中的词项（搜索时会被忽略），按出现顺序、不重复
+    pub fn missing_terms(&self, text: &str) -> Vec<String> {
+        let mut missing = vec![];
+        for token in self.count_vectorizer.analyze(text) {
+            if !self.contains_term(&token.term) && !missing.contains(&token.term) {
+                missing.push(token.term);
+            }
+        }
+        missing
+    }
+
+    /// 为搜索词中不在索引中的词给出纠错建议，每个词最多一个
+    ///
+    /// 单个汉字常常是被切开的错别字（如“研究僧”切分为“研究”“僧”），同时尝试与前后的词合并后纠错
+    pub fn corrections(&self, text: &str) -> Vec<Correction> {
+        let chars = text.chars().collect::<Vec<_>>();
+        let tokens = self.count_vectorizer.analyze(text);
+        let words = full_words(&tokens).collect::<Vec<_>>();
+        let surface =
+            |from: &Token, to: &Token| chars[from.start..to.end].iter().collect::<String>();
+
+        let mut corrections: Vec<Correction> = vec![];
+        for (i, word) in words.iter().enumerate() {
+            if self.contains_term(&word.term) {
+                continue;
+            }
+            let mut candidates = vec![surface(word, word)];
+            if word.end - word.start == 1 && is_chinese(&word.term) {
+                if let Some(prev) = i.checked_sub(1).map(|i| words[i]) {
+                    if prev.end == word.start {
+                        candidates.push(surface(prev, word));
+                    }
+                }
+                if let Some(next) = words.get(i + 1) {
+                    if next.start == word.end {
+                        candidates.push(surface(word, next));
+                    }
+                }
+            }
+
+            let best = candidates
+                .iter()
+                .filter_map(|original| self.similar_terms(original, 1).into_iter().next())
+                .min_by(|a, b| a.rank().cmp(&b.rank()));
+            if let Some(best) = best {
+                if !corrections.iter().any(|c| c.original == best.original) {
+                    corrections.push(best);
+                }
+            }
+        }
+        corrections
+    }
+
+    /// 索引中与 `word` 相近的词项，最好的在前
+    ///
+    /// 英文按词干的编辑距离（不超过 1 或 2，取决于长度）；中文允许一个字不同，
+    /// 或者长度相同且拼音相同（模糊音视为相同），按拼音距离优先排序
+    pub fn similar_terms(&self, word: &str, limit: usize) -> Vec<Correction> {
+        let term = self.count_vectorizer.normalize(word);
+        let chars = term.chars().collect::<Vec<_>>();
+        let chinese = is_chinese(&term);
+        let pinyin = chinese.then(|| pinyin(&term));
+        let max_distance = match (chinese, chars.len()) {
+            (true, 1) => 0,
+            (true, _) => 1,
+            (false, 0..=2) => 0,
+            (false, 3..=5) => 1,
+            (false, _) => 2,
+        };
+
+        let mut corrections = vec![];
+        for (candidate, &t) in &self.count_vectorizer.vocab().id_map {
+            if candidate == &term || is_chinese(candidate) != chinese {
+                continue;
+            }
+            let candidate_chars = candidate.chars().collect::<Vec<_>>();
+            let same_len = candidate_chars.len() == chars.len();
+            if candidate_chars.len().abs_diff(chars.len()) > max_distance && !(chinese && same_len)
+            {
+                continue;
+            }
+            let df = self.stats.df(t);
+            if df == 0 {
+                continue;
+            }
+
+            let distance = levenshtein(&chars, &candidate_chars);
+            let pinyin_distance = pinyin
+                .as_ref()
+                .map(|p| levenshtein(p, &self::pinyin(candidate)));
+            let homophone = same_len && pinyin_distance == Some(0);
+            if distance > max_distance && !homophone {
+                continue;
+            }
+            corrections.push(Correction {
+                original: word.to_owned(),
+                suggestion: candidate.to_owned(),
+                distance,
+                pinyin_distance,
+                df,
+            });
+        }
+
+        corrections.sort_by(|a, b| a.rank().cmp(&b.rank()));
+        corrections.truncate(limit);
+        corrections
+    }
+}
+
+/// 是否全部为汉字
+fn is_chinese(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.to_pinyin().is_some())
+}
+
+/// 不带声调的拼音字母（模糊音 zh/z、ch/c、sh/s、ng/n 视为相同），音节之间用空格分隔
+fn pinyin(s: &str) -> Vec<char> {
+    let syllables = s
+        .to_pinyin()
+        .map(|p| {
+            let p = p.map_or("", |p| p.plain());
+            let p = ["zh", "ch", "sh"]
+                .iter()
+                .find_map(|initial| {
+                    p.strip_prefix(initial)
+                        .map(|rest| format!("{}{}", &initial[..1], rest))
+                })
+                .unwrap_or_else(|| p.to_owned());
+            match p.strip_suffix("ng") {
+                Some(rest) => format!("{}n", rest),
+                None => p,
+            }
+        })
+        .collect::<Vec<_>>();
+    syllables.join(" ").chars().collect()
+}
+
+/// 编辑距离（插入、删除、替换）
+pub fn levenshtein<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    let mut curr = vec![0; b.len() + 1];
+    for (i, x) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        for (j, y) in b.iter().enumerate() {
+            let substitution = prev[j] + usize::from(x != y);
+            curr[j + 1] = substitution.min(prev[j + 1] + 1).min(curr[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Config,
+        dataset::{Dataset, Doc},
+    };
+
+    fn suggestions(corrections: &[Correction]) -> Vec<(&str, &str)> {
+        corrections
+            .iter()
+            .map(|c| (c.original.as_str(), c.suggestion.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_spell() {
+        assert_eq!(levenshtein(&['a', 'b', 'c'], &['a', 'c']), 1);
+        assert_eq!(levenshtein(&['a', 'b'], &['b', 'a']), 2);
+        assert_eq!(pinyin("生"), pinyin("僧"));
+
+        let dataset = Dataset {
+            docs: vec![
+                Doc::builder().with_url("1").with_title("研究生招生简章"),
+                Doc::builder().with_url("2").with_title("本科生选课通知"),
+                Doc::builder()
+                    .with_url("3")
+                    .with_title("人工智能讲座 Lectures on AI"),
+            ],
+        };
+        let core = Core::new(&dataset, &Config::default()).unwrap();
+
+        assert_eq!(core.missing_terms("研究生 招生"), Vec::<String>::new());
+        assert_eq!(core.missing_terms("选客 lectrues"), vec!["选客", "lectru"]);
+
+        // 同音字优先于字形相近的词
+        assert_eq!(
+            suggestions(&core.corrections("选客 lectrues")),
+            vec![("选客", "选课"), ("lectrues", "lectur")]
+        );
+        // 被切开的错别字与相邻的词合并后纠错
+        assert_eq!(
+            suggestions(&core.corrections("研究僧")),
+            vec![("研究僧", "研究生")]
+        );
+        // 没有相近的词项
+        assert!(core.corrections("天气").is_empty());
+    }
+}
@@ -0,0 +1,154 @@
+use crate::{
+    config::{parse_word_list, StopwordsConfig},
+    core::{Token, TokenFilter},
+};
+use std::collections::HashSet;
+
+/// 内置的中文停用词表
+const ZH: &str = include_str!("stopwords/zh.txt");
+/// 内置的英文停用词表
+const EN: &str = include_str!("stopwords/en.txt");
+
+/// 去掉停用词，以及不含字母和数字的词（标点、符号）
+///
+/// 英文按小写匹配，应放在转小写之后、提取词干之前
+#[derive(Debug, Default)]
+pub struct StopwordFilter {
+    words: HashSet<String>,
+    punctuation: bool,
+}
+
+impl StopwordFilter {
+    pub fn new(words: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        Self {
+            words: words
+                .into_iter()
+                .map(|w| w.as_ref().to_ascii_lowercase())
+                .collect(),
+            punctuation: false,
+        }
+    }
+
+    /// 内置的中英文停用词表
+    pub fn builtin() -> Self {
+        Self::new(parse_word_list(ZH).into_iter().chain(parse_word_list(EN)))
+    }
+
+    /// 按配置创建，停用词表文件已在读取配置时读取
+    pub fn from_config(config: &StopwordsConfig) -> Self {
+        let mut filter = if config.builtin {
+            Self::builtin()
+        } else {
+            Self::default()
+        };
+        filter.extend(&config.words);
+        filter.punctuation(config.punctuation)
+    }
+
+    /// 是否去掉标点、符号
+    pub fn punctuation(mut self, punctuation: bool) -> Self {
+        self.punctuation = punctuation;
+        self
+    }
+
+    /// 追加停用词
+    pub fn extend(&mut self, words: impl IntoIterator<Item = impl AsRef<str>>) {
+        self.words
+            .extend(words.into_iter().map(|w| w.as_ref().to_ascii_lowercase()));
+    }
+
+    /// 停用词个数
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty() && !self.punctuation
+    }
+
+    /// 词是否被去掉
+    pub fn is_stopword(&self, term: &str) -> bool {
+        self.words.contains(term) || (self.punctuation && !term.chars().any(char::is_alphanumeric))
+    }
+}
+
+impl TokenFilter for StopwordFilter {
+    fn filter(&self, mut tokens: Vec<Token>) -> Vec<Token> {
+        tokens.retain(|t| !self.is_stopword(&t.term));
+        tokens
+    }
+
+    fn filter_with_removed(&self, tokens: Vec<Token>, removed: &mut Vec<String>) -> Vec<Token> {
+        let (removed_tokens, tokens): (Vec<_>, Vec<_>) =
+            tokens.into_iter().partition(|t| self.is_stopword(&t.term));
+        removed.extend(removed_tokens.into_iter().map(|t| t.term));
+        tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{AnalyzerConfig, Config},
+        core::{Analyzer, AnalyzerChain, Core},
+        dataset::{Dataset, Doc},
+    };
+
+    #[test]
+    fn test_stopwords() {
+        let filter = StopwordFilter::builtin().punctuation(true);
+        assert!(filter.is_stopword("的"));
+        assert!(filter.is_stopword("the"));
+        assert!(filter.is_stopword("，"));
+        assert!(!filter.is_stopword("研究生"));
+        assert!(!filter.is_stopword("2023"));
+
+        // 在转小写之后、提取词干之前去掉，位置不变
+        let analyzer = AnalyzerChain::default();
+        let (tokens, removed) = analyzer.analyze_with_removed("The 研究生的招生，Lectures");
+        let terms = tokens
+            .iter()
+            .map(|t| (t.term.as_str(), t.position))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            terms,
+            vec![("研究", 1), ("研究生", 1), ("招生", 3), ("lectur", 5)]
+        );
+        // 全角逗号已规范化为半角
+        assert_eq!(removed, vec!["the", "的", ","]);
+        assert_eq!(analyzer.normalize("The"), None);
+
+        // 自定义停用词表
+        let mut config = AnalyzerConfig::default();
+        config.stopwords.builtin = false;
+        config.stopwords.words = vec!["招生".to_owned(), "Lectures".to_owned()];
+        let analyzer = AnalyzerChain::from_config(&config);
+        let terms = analyzer
+            .analyze("the 招生 lectures")
+            .into_iter()
+            .map(|t| t.term)
+            .collect::<Vec<_>>();
+        assert_eq!(terms, vec!["the"]);
+
+        // 停用词不进入词汇表，统计去掉的倒排记录数（同一文档中只计一次）
+        let mut dataset = Dataset {
+            docs: vec![
+                Doc::builder()
+                    .with_url("1")
+                    .with_title("关于研究生的招生")
+                    .with_text("研究生的考试。"),
+                Doc::builder()
+                    .with_url("2")
+                    .with_title("选课通知")
+                    .with_text("选课"),
+            ],
+        };
+        let mut core = Core::new(&dataset, &Config::default()).unwrap();
+        assert!(!core.contains_term("的"));
+        assert_eq!(core.count_vectorizer.vocab().id("的"), None);
+        assert_eq!(core.stats.removed_postings, 3);
+        core.update(&mut dataset, vec![], &[0]);
+        assert_eq!(core.stats.removed_postings, 0);
+    }
+}
@@ -0,0 +1,232 @@
+use crate::core::{compact_ids, full_words, Core, Token};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// 标题片段的最大长度（字符）
+const MAX_FRAGMENT_LEN: usize = 20;
+
+/// 补全建议
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Suggestion {
+    pub text: String,
+    /// 词项为文档频率，标题片段为包含它的标题数
+    pub count: usize,
+}
+
+impl Core {
+    /// 以 `prefix` 开头的词项，按文档频率递减排列，不含只出现在已删除文档中的词项
+    ///
+    /// 英文同时按小写的原词和词干匹配（如 `lectures` 的词干为 `lectur`），返回的是索引中的词项
+    pub fn complete(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        let prefix = prefix.trim();
+        if prefix.is_empty() {
+            return vec![];
+        }
+        let mut prefixes = vec![prefix.to_lowercase()];
+        let normalized = self.count_vectorizer.normalize(prefix);
+        if !prefixes.contains(&normalized) {
+            prefixes.push(normalized);
+        }
+
+        let mut terms = HashMap::new();
+        for prefix in &prefixes {
+            for (word, t) in self.count_vectorizer.vocab().prefix(prefix) {
+                let df = self.stats.df(t);
+                if df > 0 {
+                    terms.insert(word, df);
+                }
+            }
+        }
+
+        let mut suggestions = terms
+            .into_iter()
+            .map(|(word, df)| Suggestion {
+                text: word.to_owned(),
+                count: df,
+            })
+            .collect::<Vec<_>>();
+        suggestions.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.text.cmp(&b.text)));
+        suggestions.truncate(limit);
+        suggestions
+    }
+}
+
+/// 标题片段的倒排表：每个标题中从词的开头开始、在完整的词的边界结束的片段（不跨越标点和空白，
+/// 最多 [`MAX_FRAGMENT_LEN`] 个字符），到包含它的文档（全局id，含已删除的文档），用于按前缀补全
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TitleFragments {
+    /// (ASCII 转小写的片段, 片段) -> 文档（递增），按字典序排列
+    fragments: BTreeMap<(String, String), Vec<usize>>,
+}
+
+impl TitleFragments {
+    /// 添加新文档的标题片段，id 从 `base` 开始依次分配（大于已有的文档id）
+    /// * `titles`: 新文档的标题, shape(nd_new,)
+    /// * `analyzed`: 标题的分词结果, shape(nd_new,)
+    pub fn add(&mut self, base: usize, titles: &[String], analyzed: &[Vec<Token>]) {
+        for (d, (title, tokens)) in (base..).zip(titles.iter().zip(analyzed)) {
+            for fragment in fragments(title, tokens) {
+                let key = (fragment.to_ascii_lowercase(), fragment);
+                self.fragments.entry(key).or_default().push(d);
+            }
+        }
+    }
+
+    /// 去掉合并段时清除的文档，其后的文档id前移
+    /// * `purged`: 清除的文档（递增）
+    pub fn compact(&mut self, purged: &[usize]) {
+        for docs in self.fragments.values_mut() {
+            compact_ids(docs, purged);
+        }
+        self.fragments.retain(|_, docs| !docs.is_empty());
+    }
+
+    /// 以 `prefix` 开头且比它长的片段，按包含它的标题数递减排列，数量相同时短的在前；英文不区分大小写
+    /// * `is_live`: 文档是否未删除
+    pub fn complete(
+        &self,
+        prefix: &str,
+        limit: usize,
+        is_live: impl Fn(usize) -> bool,
+    ) -> Vec<Suggestion> {
+        let prefix = prefix.trim().to_ascii_lowercase();
+        if prefix.is_empty() {
+            return vec![];
+        }
+        let prefix_len = prefix.chars().count();
+
+        let mut suggestions = self
+            .fragments
+            .range((prefix.clone(), String::new())..)
+            .take_while(|((lower, _), _)| lower.starts_with(&prefix))
+            .filter(|((_, fragment), _)| fragment.chars().count() > prefix_len)
+            .filter_map(|((_, fragment), docs)| {
+                let count = docs.iter().filter(|&&d| is_live(d)).count();
+                (count > 0).then(|| Suggestion {
+                    text: fragment.clone(),
+                    count,
+                })
+            })
+            .collect::<Vec<_>>();
+        suggestions.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then(a.text.chars().count().cmp(&b.text.chars().count()))
+                .then_with(|| a.text.cmp(&b.text))
+        });
+        suggestions.truncate(limit);
+        suggestions
+    }
+}
+
+impl Core {
+    /// 未删除的标题中以 `prefix` 开头的片段，见 [`TitleFragments::complete`]
+    pub fn complete_titles(&self, prefix: &str, limit: usize) -> Vec<Suggestion> {
+        self.title_fragments
+            .complete(prefix, limit, |d| !self.is_deleted(d))
+    }
+}
+
+/// 标题中从词（含子词）的开头开始、在完整的词的结尾结束的片段，不跨越标点和空白
+/// * `tokens`: 标题的分词结果
+fn fragments(title: &str, tokens: &[Token]) -> HashSet<String> {
+    let chars = title.chars().collect::<Vec<_>>();
+    let ends = full_words(tokens).map(|t| t.end).collect::<HashSet<_>>();
+    let starts = tokens.iter().map(|t| t.start).collect::<BTreeSet<_>>();
+
+    let mut fragments = HashSet::new();
+    for start in starts {
+        let max_end = chars.len().min(start + MAX_FRAGMENT_LEN);
+        for end in start + 1..=max_end {
+            if !chars[end - 1].is_alphanumeric() {
+                break;
+            }
+            if ends.contains(&end) {
+                fragments.insert(chars[start..end].iter().collect());
+            }
+        }
+    }
+    fragments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Config,
+        dataset::{Dataset, Doc},
+    };
+
+    fn texts(suggestions: &[Suggestion]) -> Vec<(&str, usize)> {
+        suggestions
+            .iter()
+            .map(|s| (s.text.as_str(), s.count))
+            .collect()
+    }
+
+    #[test]
+    fn test_suggest() {
+        let mut dataset = Dataset {
+            docs: vec![
+                Doc::builder().with_url("1").with_title("研究生招生简章"),
+                Doc::builder()
+                    .with_url("2")
+                    .with_title("研究生招生考试安排"),
+                Doc::builder()
+                    .with_url("3")
+                    .with_title("研究院讲座 Lectures on AI"),
+                Doc::builder().with_url("4").with_title("研究生奖学金评选"),
+            ],
+        };
+        let mut core = Core::new(&dataset, &Config::default()).unwrap();
+
+        // 按文档频率排列
+        let terms = core.complete("研究", 10);
+        assert_eq!(
+            terms[0],
+            Suggestion {
+                text: "研究".to_owned(),
+                count: 4
+            }
+        );
+        assert_eq!(
+            terms[1],
+            Suggestion {
+                text: "研究生".to_owned(),
+                count: 3
+            }
+        );
+        assert!(terms.iter().any(|s| s.text == "研究院"));
+        assert_eq!(core.complete("研究", 1).len(), 1);
+
+        // 英文按词干匹配
+        assert_eq!(texts(&core.complete("Lectures", 10)), vec![("lectur", 1)]);
+
+        // 标题片段按标题数排列，在词的边界结束，不跨越空白
+        assert_eq!(
+            texts(&core.complete_titles("研究生", 4)),
+            vec![
+                ("研究生招生", 2),
+                ("研究生奖学金", 1),
+                ("研究生招生简章", 1),
+                ("研究生招生考试", 1)
+            ]
+        );
+        assert_eq!(
+            texts(&core.complete_titles("lecture", 10)),
+            vec![("Lectures", 1)]
+        );
+
+        // 增量添加的新词按字典序归并
+        core.upsert(
+            &mut dataset,
+            vec![Doc::builder().with_url("5").with_title("研究所开放日")],
+        );
+        assert!(core.complete("研究", 10).iter().any(|s| s.text == "研究所"));
+
+        // 只出现在已删除文档中的词项和片段不再出现
+        core.update(&mut dataset, vec![], &[2]);
+        assert!(core.complete("研究院", 10).is_empty());
+        assert!(core.complete_titles("lecture", 10).is_empty());
+    }
+}
@@ -1,54 +1,210 @@
-use sprs::{CsMat, CsMatView};
+use crate::core::{max_merge, remove_ids, IndexStats, Segment, TermScore};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sprs::{CsMat, CsMatView, CsVec, CsVecView};
+use std::sync::Arc;
 
-#[derive(Debug)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct TfidfVectorizer {
     /// 逆文档频率, shape(nt,)
     idf: Vec<f64>,
-    /// tf-idf, shape(nd,nt)
-    tf_idf: CsMat<f64>,
+    /// 每个文档（全局id，含已删除的文档）的 TF-IDF 向量的模长，随 IDF 重新计算, shape(nd,)
+    norms: Vec<f64>,
+    /// 每个文档（全局id，含已删除的文档）的所有字词的出现次数之和，与模长一起计算, shape(nd,)
+    n_tokens: Vec<usize>,
+}
+
+impl Default for TfidfVectorizer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TfidfVectorizer {
     pub fn new() -> Self {
         Self {
             idf: vec![],
-            tf_idf: CsMat::zero((0, 0)),
+            norms: vec![],
+            n_tokens: vec![],
         }
     }
 
-    /// * `x`: 所有文档词频, shape(nd,nt)
-    pub fn fit(&mut self, x: CsMatView<usize>) {
+    /// * `stats`: 所有文档（所有段）的统计量
+    pub fn fit(&mut self, stats: &IndexStats) {
         tracing::info!("[TfidfVectorizer] 计算 IDF");
         let start_time = std::time::Instant::now();
 
-        let (nd, nt) = x.shape();
-
         // 计算逆文档频率 Inverse Document Frequency
         // idf(t) = log10(N / df(t))
         // 分子：语料库中的文档总数
-        // 分母：包含词项t的文档数
-        let mut count = vec![0; nt];
-        for (&v, (_d, t)) in x {
-            if v > 0 {
-                count[t] += 1;
-            }
-        }
+        // 分母：包含词项t的文档数（文档都被删除的词项记为 0）
+        self.idf = stats
+            .df
+            .iter()
+            .map(|&df| match df {
+                0 => 0.0,
+                df => (stats.n_docs as f64 / df as f64).log10(),
+            })
+            .collect();
+
+        tracing::info!(
+            "[TfidfVectorizer] IDF 计算完成，用时 {:?}",
+            start_time.elapsed()
+        );
+    }
 
-        self.idf = vec![0.0; nt];
-        for t in 0..nt {
-            self.idf[t] = (nd as f64 / count[t] as f64).log10();
+    /// 计算所有文档的 TF-IDF 向量的模长和词数，需在 [`TfidfVectorizer::fit`] 之后调用
+    /// * `segments`: 所有段
+    /// * `nd`: 文档总数（含已删除的文档）
+    pub fn fit_norms(&mut self, segments: &[Arc<Segment>], nd: usize) {
+        tracing::info!("[TfidfVectorizer] 计算文档向量的模长");
+        let start_time = std::time::Instant::now();
+
+        let mut norms = vec![0.0; nd];
+        let mut n_tokens = vec![0; nd];
+        for segment in segments {
+            // 段内的文档并行计算
+            let segment_norms = (0..segment.len())
+                .into_par_iter()
+                .map(|i| {
+                    let x = segment.row(i);
+                    let len = x.data().iter().sum::<usize>();
+                    let norm = x
+                        .iter()
+                        .map(|(t, &v)| (v as f64 / len as f64 * self.idf(t)).powi(2))
+                        .sum::<f64>()
+                        .sqrt();
+                    (norm, len)
+                })
+                .collect::<Vec<_>>();
+            for (&d, (norm, len)) in segment.doc_ids().iter().zip(segment_norms) {
+                norms[d] = norm;
+                n_tokens[d] = len;
+            }
         }
+        self.norms = norms;
+        self.n_tokens = n_tokens;
 
         tracing::info!(
-            "[TfidfVectorizer] IDF 计算完成，用时 {:?}",
+            "[TfidfVectorizer] 模长计算完成，用时 {:?}",
             start_time.elapsed()
         );
+    }
+
+    /// 去掉合并段时清除的文档的模长和词数
+    /// * `purged`: 清除的文档（递增）
+    pub fn compact(&mut self, purged: &[usize]) {
+        remove_ids(&mut self.norms, purged);
+        remove_ids(&mut self.n_tokens, purged);
+    }
 
-        self.tf_idf = self.transform(x);
+    fn idf(&self, t: usize) -> f64 {
+        self.idf.get(t).copied().unwrap_or(0.0)
     }
 
-    pub fn get_tf_idf(&self) -> CsMatView<f64> {
-        self.tf_idf.view()
+    /// 文档d的 TF-IDF 向量的模长
+    pub fn norm(&self, d: usize) -> f64 {
+        self.norms.get(d).copied().unwrap_or(0.0)
+    }
+
+    /// 余弦相似度，只累加文档d中出现的搜索词项，文档的词数和向量的模长使用预先计算的值
+    /// * `q`: 搜索词 TF-IDF, shape(nt,)
+    /// * `d`: 文档的全局id
+    /// * `matched`: 文档d中出现的搜索词项及其词频（来自倒排记录）
+    pub fn score_doc(&self, q: CsVecView<f64>, d: usize, matched: &[(usize, usize)]) -> f64 {
+        let norm = q.dot(&q).sqrt() * self.norm(d);
+        if norm == 0.0 {
+            return 0.0; // 零向量（如只有 NOT 的查询）
+        }
+        let n_tokens = self.n_tokens.get(d).copied().unwrap_or(0) as f64;
+        let dot = matched
+            .iter()
+            .filter_map(|&(t, v)| {
+                // w(t, d) = tf(t, d) * idf(t)
+                let &q_t = q.get(t)?;
+                Some(q_t * v as f64 / n_tokens * self.idf(t))
+            })
+            .sum::<f64>();
+        dot / norm
+    }
+
+    /// 余弦相似度，文档的 TF-IDF 向量即时计算
+    /// * `q`: 搜索词 TF-IDF, shape(nt,)
+    /// * `x`: 文档词频, shape(nt,)
+    pub fn score(&self, q: CsVecView<f64>, x: CsVecView<usize>) -> f64 {
+        let n_tokens = x.data().iter().sum::<usize>() as f64;
+        let mut dot = 0.0;
+        let mut norm = 0.0;
+        for (t, &v) in x.iter() {
+            let w = v as f64 / n_tokens * self.idf(t);
+            norm += w * w;
+            if let Some(&q) = q.get(t) {
+                dot += q * w;
+            }
+        }
+
+        let norm = q.dot(&q).sqrt() * norm.sqrt();
+        if norm == 0.0 {
+            return 0.0; // 零向量（如只有 NOT 的查询）
+        }
+        dot / norm
+    }
+
+    /// 余弦相似度中每个搜索词项的贡献 `q_t * w_t / (|q| * |w|)`
+    /// * `q`: 搜索词 TF-IDF, shape(nt,)
+    /// * `x`: 文档词频, shape(nt,)
+    pub fn explain(&self, q: CsVecView<f64>, x: CsVecView<usize>) -> Vec<TermScore> {
+        let w = self.transform_row(x);
+        let norm = q.dot(&q).sqrt() * w.dot(&w).sqrt();
+        q.iter()
+            .map(|(t, &q_t)| {
+                let w_t = w.get(t).copied().unwrap_or(0.0);
+                TermScore {
+                    id: t,
+                    tf: x.get(t).copied().unwrap_or(0),
+                    idf: self.idf(t),
+                    query_weight: q_t,
+                    weight: w_t,
+                    contribution: if norm == 0.0 { 0.0 } else { q_t * w_t / norm },
+                }
+            })
+            .collect()
+    }
+
+    /// 每个词项在归一化的文档向量中的最大权重 `max_d w_td / |w_d|`，
+    /// 乘以 `q_t / |q|` 即为词项对余弦相似度贡献的上界
+    /// * `rows`: 所有文档的全局id和词频, shape(nt,)，并行遍历
+    /// * `nt`: 词汇量
+    pub fn max_weights(
+        &self,
+        rows: impl ParallelIterator<Item = (usize, CsVec<usize>)>,
+        nt: usize,
+    ) -> Vec<f64> {
+        rows.fold(
+            || vec![0.0; nt],
+            |mut max, (d, x)| {
+                let norm = self.norm(d);
+                if norm == 0.0 {
+                    return max;
+                }
+                for (t, &w_t) in self.transform_row(x.view()).iter() {
+                    max[t] = f64::max(max[t], w_t / norm);
+                }
+                max
+            },
+        )
+        .reduce(|| vec![0.0; nt], max_merge)
+    }
+
+    /// * `x`: 一个文档的词频, shape(nt,)
+    /// * `returns`: 文档向量, 值为 TF-IDF, shape(nt,)
+    pub fn transform_row(&self, x: CsVecView<usize>) -> CsVec<f64> {
+        let n_tokens = x.data().iter().sum::<usize>() as f64;
+        let (indices, data) = x
+            .iter()
+            .map(|(t, &v)| (t, v as f64 / n_tokens * self.idf(t)))
+            .unzip();
+        CsVec::new(x.dim(), indices, data)
     }
 
     /// * `x`: 所有文档词频, shape(nd,nt)
@@ -59,6 +215,9 @@ impl TfidfVectorizer {
 
         let (nd, nt) = x.shape();
 
+        let indptr = x.indptr().as_slice().unwrap().to_owned();
+        let indices = x.indices().to_owned();
+
         // 每个文档的所有字词的出现次数之和
         let mut n_tokens = vec![0; nd];
         for (&v, (d, _t)) in x {
@@ -66,8 +225,6 @@ impl TfidfVectorizer {
         }
 
         // 将词频复制一份，转换为 f64
-        let indptr = x.indptr().as_slice().unwrap().to_owned();
-        let indices = x.indices().to_owned();
         let mut data = x.data().iter().map(|&v| v as f64).collect::<Vec<_>>();
 
         for d in 0..nd {
@@ -80,7 +237,7 @@ impl TfidfVectorizer {
                 let tf = data[i] / n_tokens[d] as f64;
 
                 // 填入tf(t, d)*idf(t)
-                data[i] = tf * self.idf[t];
+                data[i] = tf * self.idf(t);
             }
         }
 
@@ -99,8 +256,11 @@ impl TfidfVectorizer {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::almost_eq::AlmostEq;
-    use sprs::CsMat;
+    use crate::{
+        almost_eq::AlmostEq,
+        core::{cs_helper, PerField},
+    };
+    use sprs::{CsMat, CsVec};
 
     #[test]
     fn test_tf_idf() {
@@ -110,7 +270,12 @@ mod tests {
         freq.insert(2, 2, 1);
 
         let mut vsm = TfidfVectorizer::new();
-        vsm.fit(freq.view());
+        vsm.fit(&IndexStats {
+            n_docs: 3,
+            df: vec![1, 1, 1],
+            field_len: PerField::default(),
+            removed_postings: 0,
+        });
         let score = vsm.transform(freq.view());
 
         let mut expected = CsMat::zero((3, 3));
@@ -119,5 +284,27 @@ mod tests {
         expected.insert(2, 2, 0.47712125471966244);
 
         assert!(score.to_dense().almost_eq(&expected.to_dense(), 1e-6));
+
+        // 与文档自身的余弦相似度为 1
+        let q = score.outer_view(0).unwrap();
+        assert!(vsm
+            .score(q, freq.outer_view(0).unwrap())
+            .almost_eq(&1.0, 1e-6));
+        assert!(vsm
+            .score(q, freq.outer_view(1).unwrap())
+            .almost_eq(&0.0, 1e-6));
+        // 每个词项的贡献之和等于得分
+        let x = cs_helper::cs_mat_from_cs_vecs(&[CsVec::new(3, vec![0, 1], vec![2, 1])]);
+        let terms = vsm.explain(q, x.outer_view(0).unwrap());
+        assert_eq!(terms.len(), 1);
+        assert_eq!(terms[0].tf, 2);
+        assert!(terms[0]
+            .contribution
+            .almost_eq(&vsm.score(q, x.outer_view(0).unwrap()), 1e-6));
+
+        let q = CsVec::new(3, vec![], vec![]);
+        assert!(vsm
+            .score(q.view(), freq.outer_view(0).unwrap())
+            .almost_eq(&0.0, 1e-6));
     }
 }
@@ -0,0 +1,390 @@
+use crate::core::{Bm25Scorer, Bm25fScorer, Core, PerField, Segment, TfidfVectorizer};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sprs::CsVecView;
+use std::{cmp::Ordering, cmp::Reverse, collections::BinaryHeap, sync::Arc};
+
+/// 浮点误差的余量，得分上界乘以 `1 + EPSILON` 后仍低于阈值才跳过
+const EPSILON: f64 = 1e-9;
+
+/// 每个词项在所有文档（含已删除的文档）中对得分贡献的上界，用于 top-k 检索时跳过不可能进入前 k 名的文档
+///
+/// 依赖全局的 IDF、平均长度和文档向量的模长，随 [`Core::refresh`] 重新计算；合并段只会去掉文档，上界仍然有效
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct MaxScores {
+    /// TF-IDF：归一化的文档向量中的最大权重, shape(nt,)
+    tfidf: Vec<f64>,
+    /// BM25：得分的最大贡献, shape(nt,)
+    bm25: Vec<f64>,
+    /// BM25F：每个字段归一化词频的最大值（字段权重在搜索时才确定）, shape(nt,)
+    bm25f: PerField<Vec<f64>>,
+}
+
+impl MaxScores {
+    /// * `segments`: 所有段
+    /// * `nt`: 词汇量
+    pub fn build(
+        segments: &[Arc<Segment>],
+        nt: usize,
+        tfidf_vectorizer: &TfidfVectorizer,
+        bm25_scorer: &Bm25Scorer,
+        bm25f_scorer: &Bm25fScorer,
+    ) -> MaxScores {
+        tracing::info!("[MaxScores] 计算词项得分上界");
+        let start_time = std::time::Instant::now();
+
+        // 所有文档并行遍历
+        let docs = || {
+            segments.par_iter().flat_map(|segment| {
+                (0..segment.len())
+                    .into_par_iter()
+                    .map(move |i| (segment, i))
+            })
+        };
+        let max_scores = MaxScores {
+            tfidf: tfidf_vectorizer
+                .max_weights(docs().map(|(s, i)| (s.doc_ids()[i], s.row(i))), nt),
+            bm25: bm25_scorer.max_scores(
+                docs().map(|(s, i)| (s.row(i), s.field_len(i).iter().map(|(_, &l)| l).sum())),
+                nt,
+            ),
+            bm25f: bm25f_scorer
+                .max_field_tf(docs().map(|(s, i)| (s.field_rows(i), s.field_len(i))), nt),
+        };
+
+        tracing::info!("[MaxScores] 计算完成，用时 {:?}", start_time.elapsed());
+        max_scores
+    }
+
+    /// TF-IDF 余弦相似度中每个搜索词项贡献的上界 `q_t / |q| * max_d(w_td / |w_d|)`
+    /// * `q`: 搜索词 TF-IDF, shape(nt,)
+    pub fn tfidf(&self, q: CsVecView<f64>) -> Vec<(usize, f64)> {
+        let norm = q.dot(&q).sqrt();
+        q.iter()
+            .map(|(t, &q_t)| {
+                let max = self.tfidf.get(t).copied().unwrap_or(0.0);
+                (t, if norm == 0.0 { 0.0 } else { q_t / norm * max })
+            })
+            .collect()
+    }
+
+    /// BM25 中每个搜索词项贡献的上界
+    /// * `q`: 搜索词词频, shape(nt,)
+    pub fn bm25(&self, q: CsVecView<usize>) -> Vec<(usize, f64)> {
+        q.iter()
+            .filter(|&(_t, &v)| v > 0)
+            .map(|(t, _)| (t, self.bm25.get(t).copied().unwrap_or(0.0)))
+            .collect()
+    }
+
+    /// BM25F 中每个搜索词项贡献的上界
+    /// * `q`: 搜索词词频, shape(nt,)
+    /// * `boost`: 每个字段的权重
+    pub fn bm25f(
+        &self,
+        scorer: &Bm25fScorer,
+        q: CsVecView<usize>,
+        boost: &PerField<f64>,
+    ) -> Vec<(usize, f64)> {
+        q.iter()
+            .filter(|&(_t, &v)| v > 0)
+            .map(|(t, _)| {
+                let max_tf = self.bm25f.map(|_, max| max.get(t).copied().unwrap_or(0.0));
+                (t, scorer.upper_bound(t, &max_tf, boost))
+            })
+            .collect()
+    }
+}
+
+/// 逐个词项取最大值，用于合并并行计算的得分上界, shape(nt,)
+pub fn max_merge(a: Vec<f64>, b: Vec<f64>) -> Vec<f64> {
+    a.into_iter().zip(b).map(|(a, b)| a.max(b)).collect()
+}
+
+/// 堆中的文档：得分高的在前，得分相同时id小的在前
+#[derive(Debug, Clone, Copy)]
+struct Ranked {
+    score: f64,
+    d: usize,
+}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .total_cmp(&other.score)
+            .then_with(|| other.d.cmp(&self.d))
+    }
+}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ranked {}
+
+/// 保留得分最高的k个文档（小顶堆）
+#[derive(Debug)]
+pub struct TopK {
+    k: usize,
+    heap: BinaryHeap<Reverse<Ranked>>,
+}
+
+impl TopK {
+    pub fn new(k: usize) -> Self {
+        Self {
+            k,
+            heap: BinaryHeap::new(),
+        }
+    }
+
+    /// 已满k个时，进入前k名需要超过的得分
+    pub fn threshold(&self) -> Option<f64> {
+        match self.heap.len() >= self.k {
+            true => self.heap.peek().map(|Reverse(r)| r.score),
+            false => None,
+        }
+    }
+
+    /// 得分上界为 `bound` 的文档是否不可能进入前k名
+    pub fn can_skip(&self, bound: f64) -> bool {
+        if self.k == 0 {
+            return true;
+        }
+        self.threshold()
+            .is_some_and(|threshold| bound * (1.0 + EPSILON) < threshold)
+    }
+
+    pub fn push(&mut self, d: usize, score: f64) {
+        if self.k == 0 {
+            return;
+        }
+        self.heap.push(Reverse(Ranked { score, d }));
+        if self.heap.len() > self.k {
+            self.heap.pop();
+        }
+    }
+
+    /// 按得分递减排列（得分相同时id小的在前）
+    pub fn into_sorted_vec(self) -> Vec<(usize, f64)> {
+        // Reverse 的升序即得分的降序
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(r)| (r.d, r.score))
+            .collect()
+    }
+}
+
+/// top-k 检索的结果
+#[derive(Debug)]
+pub struct TopDocs {
+    /// 得分最高的文档及其得分，按得分递减排列
+    pub hits: Vec<(usize, f64)>,
+    /// 命中的文档数
+    pub total_hits: usize,
+    /// `total_hits` 是否准确，为 `false` 时只是下界（跳过了一些文档）
+    pub exact: bool,
+}
+
+impl Core {
+    /// 用 MaxScore 算法检索得分最高的k个文档
+    ///
+    /// 按上界从小到大排列词项，上界之和低于当前第k名得分的词项为“非必要”的：
+    /// 只含有这些词项的文档不可能进入前k名，只需遍历其余词项的文档列表；
+    /// 候选文档也只在上界之和超过阈值时才计算得分
+    /// * `bounds`: 搜索词项及其对得分贡献的上界
+    /// * `min_match`: 至少匹配的词项数
+    /// * `k`: 返回的文档数
+    /// * `accept`: 文档是否可以作为结果（未删除、满足过滤条件等）
+    /// * `score`: 文档的得分，参数为文档和其中出现的搜索词项及词频（来自倒排记录），不超过所含词项的上界之和
+    pub fn top_k(
+        &self,
+        bounds: &[(usize, f64)],
+        min_match: usize,
+        k: usize,
+        accept: impl Fn(usize) -> bool,
+        score: impl Fn(usize, &[(usize, usize)]) -> Option<f64>,
+    ) -> TopDocs {
+        tracing::info!(
+            "[Core] top-k 检索，{} 个词项，至少匹配 {} 个，k = {}",
+            bounds.len(),
+            min_match,
+            k
+        );
+        let start_time = std::time::Instant::now();
+
+        let min_match = min_match.max(1);
+        let mut top = TopK::new(k);
+        let mut total_hits = 0;
+        let mut exact = true;
+        let mut scored = 0;
+
+        // 按上界递增排列
+        let mut bounds = bounds.to_vec();
+        bounds.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+        let n = bounds.len();
+
+        for segment in &self.segments {
+            if n < min_match {
+                break;
+            }
+            let mut postings = bounds
+                .iter()
+                .map(|&(t, _)| segment.index.postings(t))
+                .collect::<Vec<_>>();
+
+            // 至少匹配 min_match 个词项的文档一定出现在任意 n - min_match + 1 个词项的文档列表中，取最短的
+            let mut covering = (0..n).collect::<Vec<_>>();
+            covering.sort_by_key(|&j| postings[j].len());
+            covering.truncate(n - min_match + 1);
+
+            loop {
+                // 非必要词项数：上界之和低于阈值的最长前缀
+                let mut sum = 0.0;
+                let non_essential = bounds
+                    .iter()
+                    .take_while(|&&(_, ub)| {
+                        sum += ub;
+                        top.can_skip(sum)
+                    })
+                    .count();
+                let lists = match n - non_essential < covering.len() {
+                    true => (non_essential..n).collect(),
+                    false => covering.clone(),
+                };
+
+                // 下一个候选文档
+                let next = lists.iter().filter_map(|&j| postings[j].doc()).min();
+                if exact && lists.len() < covering.len() {
+                    // 只遍历必要词项时，可能漏掉只含非必要词项的文档
+                    let remaining = covering.iter().any(|&j| {
+                        postings[j]
+                            .doc()
+                            .is_some_and(|i| next.is_none_or(|next| i < next))
+                    });
+                    exact &= !remaining;
+                }
+                let Some(i) = next else {
+                    break;
+                };
+
+                // 把所有文档列表移动到候选文档之后，记录匹配的词项及词频和得分上界
+                let mut matched = vec![];
+                let mut bound = 0.0;
+                for (j, list) in postings.iter_mut().enumerate() {
+                    if list.advance(i) == Some(i) {
+                        matched.push((bounds[j].0, list.tf()));
+                        bound += bounds[j].1;
+                        list.next();
+                    }
+                }
+                if matched.len() < min_match {
+                    continue;
+                }
+                let d = segment.doc_ids()[i];
+                if !accept(d) {
+                    continue;
+                }
+                total_hits += 1;
+                if top.can_skip(bound) {
+                    continue;
+                }
+                if let Some(score) = score(d, &matched) {
+                    scored += 1;
+                    top.push(d, score);
+                }
+            }
+        }
+
+        tracing::info!(
+            "[Core] top-k 检索完成，用时 {:?}，命中 {}{} 个文档，计算得分 {} 个",
+            start_time.elapsed(),
+            if exact { "" } else { "至少 " },
+            total_hits,
+            scored
+        );
+        TopDocs {
+            hits: top.into_sorted_vec(),
+            total_hits,
+            exact,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::Config,
+        dataset::{Dataset, Doc},
+    };
+
+    #[test]
+    fn test_top_k() {
+        let mut dataset = Dataset::sample();
+        let mut core = Core::new(&dataset, &Config::default()).unwrap();
+        core.upsert(
+            &mut dataset,
+            vec![
+                Doc::builder()
+                    .with_url("5")
+                    .with_title("研究生奖学金")
+                    .with_text("研究生 研究生 奖学金 讲座"),
+                Doc::builder()
+                    .with_url("6")
+                    .with_title("招生讲座")
+                    .with_text("本科生招生讲座"),
+            ],
+        );
+        core.delete_urls(&mut dataset, &["3".to_owned()]);
+
+        let q = core
+            .count_vectorizer
+            .transform(&["研究生 奖学金 讲座 招生".to_owned()]);
+        let q = q.outer_view(0).unwrap();
+        let bounds = core.max_scores.bm25(q);
+        let score = |d: usize, _: &[(usize, usize)]| {
+            let (segment, i) = core.locate(d)?;
+            let doc_len = segment.field_len(i).iter().map(|(_, &l)| l).sum();
+            Some(core.bm25_scorer.score(q, segment.row(i).view(), doc_len))
+        };
+
+        for min_match in 1..=3 {
+            // 穷举计算所有文档的得分
+            let docs = core.search(q, None, Some(min_match));
+            let mut expected = TopK::new(2);
+            for &d in docs.indices() {
+                let s = score(d, &[]).unwrap();
+                // 得分不超过所含词项的上界之和
+                let (segment, i) = core.locate(d).unwrap();
+                let bound = bounds
+                    .iter()
+                    .filter(|&&(t, _)| segment.row(i).get(t).is_some())
+                    .map(|&(_, ub)| ub)
+                    .sum::<f64>();
+                assert!(s <= bound * (1.0 + EPSILON));
+                expected.push(d, s);
+            }
+
+            let top = core.top_k(&bounds, min_match, 2, |d| !core.is_deleted(d), score);
+            assert_eq!(top.hits, expected.into_sorted_vec());
+            assert!(top.total_hits <= docs.nnz());
+            if top.exact {
+                assert_eq!(top.total_hits, docs.nnz());
+            }
+        }
+
+        // 取全部文档时不会跳过
+        let top = core.top_k(&bounds, 1, 10, |d| !core.is_deleted(d), score);
+        assert!(top.exact);
+        assert_eq!(top.total_hits, core.search(q, None, Some(1)).nnz());
+    }
+}
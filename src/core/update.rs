@@ -0,0 +1,327 @@
+use crate::{
+    config::{AnalyzerConfig, TokenizerKind, UserWord},
+    core::{locate, AnalyzerChain, Core, Field},
+    dataset::{Dataset, Doc},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
+/// 增量更新的结果
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct UpdateStats {
+    /// 新增的文档数
+    pub added: usize,
+    /// 替换（URL 相同、内容变化）的文档数
+    pub replaced: usize,
+    /// 删除的文档数
+    pub deleted: usize,
+}
+
+impl Core {
+    /// 增量更新：新文档作为一个新段加入，删除的文档记为墓碑（合并段时清除并压缩id），并更新全局统计量
+    /// * `added`: 新文档，id 从 `dataset.docs.len()` 开始依次分配
+    /// * `deleted`: 要删除的文档id
+    /// * `returns`: 新文档的id
+    pub fn update(
+        &mut self,
+        dataset: &mut Dataset,
+        added: Vec<Doc>,
+        deleted: &[usize],
+    ) -> Range<usize> {
+        tracing::info!(
+            "[Core] 开始增量更新，添加 {} 个文档，删除 {} 个文档",
+            added.len(),
+            deleted.len()
+        );
+        let start_time = std::time::Instant::now();
+
+        let start = dataset.docs.len();
+        debug_assert_eq!(start, self.num_docs);
+        self.add_segment(&added);
+        dataset.docs.extend(added);
+
+        // 只处理新删除的文档，从全局统计量中扣除
+        for &d in deleted {
+            if d >= start || self.is_deleted(d) {
+                continue;
+            }
+            self.deleted.insert(d);
+            if let Some((segment, i)) = locate(&self.segments, d) {
+                self.stats.remove_doc(&segment.field_rows(i));
+                self.stats.removed_postings -= segment.removed_postings(i);
+            }
+        }
+
+        self.refresh();
+
+        tracing::info!("[Core] 增量更新完成，用时 {:?}", start_time.elapsed());
+        start..dataset.docs.len()
+    }
+
+    /// 未删除的文档的 URL 到id的映射
+    pub fn live_urls<'a>(&self, dataset: &'a Dataset) -> HashMap<&'a str, usize> {
+        dataset
+            .docs
+            .iter()
+            .enumerate()
+            .filter(|&(d, _)| !self.is_deleted(d))
+            .map(|(d, doc)| (doc.url.as_str(), d))
+            .collect()
+    }
+
+    /// 添加文档，URL 已存在时替换旧文档（新文档使用新的id，文档id不稳定）
+    pub fn upsert(&mut self, dataset: &mut Dataset, docs: Vec<Doc>) -> UpdateStats {
+        let urls = self.live_urls(dataset);
+
+        let mut stats = UpdateStats::default();
+        let mut deleted = vec![];
+        let mut added = vec![];
+        for doc in docs {
+            match urls.get(doc.url.as_str()) {
+                Some(&d) if dataset.docs[d] == doc => continue, // 没有变化
+                Some(&d) => {
+                    deleted.push(d);
+                    stats.replaced += 1;
+                }
+                None => stats.added += 1,
+            }
+            added.push(doc);
+        }
+
+        if !added.is_empty() {
+            self.update(dataset, added, &deleted);
+        }
+        stats
+    }
+
+    /// 按 URL 删除文档
+    pub fn delete_urls(&mut self, dataset: &mut Dataset, urls: &[String]) -> UpdateStats {
+        let live = self.live_urls(dataset);
+        let deleted = urls
+            .iter()
+            .filter_map(|url| live.get(url.as_str()).copied())
+            .collect::<Vec<_>>();
+
+        let stats = UpdateStats {
+            deleted: deleted.len(),
+            ..Default::default()
+        };
+        if !deleted.is_empty() {
+            self.update(dataset, vec![], &deleted);
+        }
+        stats
+    }
+
+    /// 按新的用户词典重新创建分析器，并重新分词包含有变化（新增、删除、词频或词性改变）的词的文档，
+    /// 这些文档作为新文档替换旧文档
+    ///
+    /// 词典总词频的变化对其他文档切分的影响很小，不重新分词
+    /// * `config`: 分析器配置，`user_words` 为新的用户词典
+    /// * `returns`: `replaced` 为重新分词的文档数
+    pub fn reload_user_dict(
+        &mut self,
+        dataset: &mut Dataset,
+        config: &AnalyzerConfig,
+    ) -> UpdateStats {
+        let changed = changed_words(&self.user_dict, &config.user_words);
+        tracing::info!("[Core] 更新用户词典，{} 个词有变化", changed.len());
+        self.count_vectorizer
+            .set_analyzer(AnalyzerChain::from_config(config));
+        self.user_dict = config.user_words.clone();
+        if changed.is_empty() || config.tokenizer != TokenizerKind::Jieba {
+            return UpdateStats::default();
+        }
+
+        // jieba 切分的是字符过滤（规范化）之后的文本
+        let analyzer = self.count_vectorizer.analyzer();
+        let affected = (0..dataset.docs.len())
+            .filter(|&d| !self.is_deleted(d))
+            .filter(|&d| {
+                Field::ALL.iter().any(|field| {
+                    let (text, _) = analyzer.filter_chars(&field.extract(&dataset.docs[d]));
+                    changed.iter().any(|word| text.contains(word.as_str()))
+                })
+            })
+            .collect::<Vec<_>>();
+        let stats = UpdateStats {
+            replaced: affected.len(),
+            ..Default::default()
+        };
+        if !affected.is_empty() {
+            let docs = affected.iter().map(|&d| dataset.docs[d].clone()).collect();
+            self.update(dataset, docs, &affected);
+        }
+        stats
+    }
+
+    /// 与重新读取的数据集同步：新增、替换内容变化的文档，删除已不存在的文档
+    pub fn sync(&mut self, dataset: &mut Dataset, latest: Dataset) -> UpdateStats {
+        let mut stats = UpdateStats::default();
+        let mut removed = self
+            .live_urls(dataset)
+            .into_iter()
+            .map(|(url, d)| (url.to_owned(), d))
+            .collect::<HashMap<_, _>>();
+
+        let mut deleted = vec![];
+        let mut added = vec![];
+        for doc in latest.docs {
+            match removed.remove(&doc.url) {
+                Some(d) if dataset.docs[d] == doc => continue, // 没有变化
+                Some(d) => {
+                    deleted.push(d);
+                    stats.replaced += 1;
+                }
+                None => stats.added += 1,
+            }
+            added.push(doc);
+        }
+
+        // 剩下的是已不存在的文档
+        stats.deleted = removed.len();
+        deleted.extend(removed.into_values());
+
+        if !added.is_empty() || !deleted.is_empty() {
+            self.update(dataset, added, &deleted);
+        }
+        stats
+    }
+}
+
+/// 新旧用户词典中有变化的词（后出现的覆盖先出现的）
+fn changed_words<'a>(old: &'a [UserWord], new: &'a [UserWord]) -> HashSet<String> {
+    let entries = |words: &'a [UserWord]| {
+        words
+            .iter()
+            .map(|w| (w.word.as_str(), w))
+            .collect::<HashMap<_, _>>()
+    };
+    let (old, new) = (entries(old), entries(new));
+    old.keys()
+        .chain(new.keys())
+        .filter(|word| old.get(*word) != new.get(*word))
+        .map(|word| word.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, core::Query};
+
+    fn search(core: &Core, dataset: &Dataset, keyword: &str) -> Vec<usize> {
+        Query::parse(keyword)
+            .evaluate(core, dataset)
+            .unwrap()
+            .indices()
+            .iter()
+            .copied()
+            .filter(|&d| !core.is_deleted(d))
+            .collect()
+    }
+
+    #[test]
+    fn test_update() {
+        let mut dataset = Dataset {
+            docs: vec![
+                Doc::builder().with_url("1").with_title("研究生招生"),
+                Doc::builder().with_url("2").with_title("本科生选课"),
+            ],
+        };
+        let mut core = Core::new(&dataset, &Config::default()).unwrap();
+        assert_eq!(search(&core, &dataset, "招生"), vec![0]);
+
+        // 新增文档，词汇表增长
+        let stats = core.upsert(
+            &mut dataset,
+            vec![Doc::builder().with_url("3").with_title("奖学金评选")],
+        );
+        assert_eq!(stats.added, 1);
+        assert_eq!(search(&core, &dataset, "奖学金"), vec![2]);
+
+        // 内容没变化时不处理
+        let stats = core.upsert(
+            &mut dataset,
+            vec![Doc::builder().with_url("3").with_title("奖学金评选")],
+        );
+        assert_eq!(stats, UpdateStats::default());
+
+        // 按 URL 替换
+        let stats = core.upsert(
+            &mut dataset,
+            vec![Doc::builder().with_url("1").with_title("研究生奖学金")],
+        );
+        assert_eq!(stats.replaced, 1);
+        assert_eq!(search(&core, &dataset, "招生"), Vec::<usize>::new());
+        assert_eq!(search(&core, &dataset, "奖学金"), vec![2, 3]);
+
+        // 删除
+        let stats = core.delete_urls(&mut dataset, &["3".to_owned()]);
+        assert_eq!(stats.deleted, 1);
+        assert_eq!(search(&core, &dataset, "奖学金"), vec![3]);
+
+        // 同步
+        let latest = Dataset {
+            docs: vec![
+                Doc::builder().with_url("1").with_title("研究生奖学金"),
+                Doc::builder().with_url("4").with_title("讲座"),
+            ],
+        };
+        let stats = core.sync(&mut dataset, latest);
+        assert_eq!(
+            stats,
+            UpdateStats {
+                added: 1,
+                replaced: 0,
+                deleted: 1
+            }
+        );
+        assert_eq!(search(&core, &dataset, "选课"), Vec::<usize>::new());
+        assert_eq!(search(&core, &dataset, "讲座"), vec![4]);
+    }
+
+    #[test]
+    fn test_user_dict() {
+        let words =
+            crate::config::parse_user_dict("# 校内用语\n公文通 10 n\n粤海校区 nz\nai实验室\n");
+        assert_eq!(words[0].freq, Some(10));
+        assert_eq!(words[1].freq, None);
+        assert_eq!(words[1].tag.as_deref(), Some("nz"));
+
+        let mut dataset = Dataset {
+            docs: vec![
+                Doc::builder().with_url("1").with_title("公文通发布通知"),
+                Doc::builder().with_url("2").with_title("研究生招生"),
+                Doc::builder().with_url("3").with_title("ＡＩ实验室开放"),
+            ],
+        };
+        let mut core = Core::new(&dataset, &Config::default()).unwrap();
+        assert!(!core.contains_term("公文通"));
+
+        // 只重新分词包含变化的词的文档
+        let mut config = AnalyzerConfig {
+            user_words: words,
+            ..Default::default()
+        };
+        // 按规范化之后的文本匹配（全角转半角、大小写折叠）
+        let stats = core.reload_user_dict(&mut dataset, &config);
+        assert_eq!(stats.replaced, 2);
+        assert!(core.contains_term("公文通"));
+        assert!(core.contains_term("ai实验室"));
+        assert_eq!(search(&core, &dataset, "公文通"), vec![3]);
+        assert_eq!(search(&core, &dataset, "招生"), vec![1]);
+
+        // 词典没有变化时不处理
+        let stats = core.reload_user_dict(&mut dataset, &config);
+        assert_eq!(stats, UpdateStats::default());
+
+        // 删除词
+        config.user_words.clear();
+        let stats = core.reload_user_dict(&mut dataset, &config);
+        assert_eq!(stats.replaced, 2);
+        assert!(!core.contains_term("公文通"));
+    }
+}
@@ -1,21 +1,48 @@
+use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Deserialize, Serialize)]
 pub struct Vocabulary {
     pub set: HashSet<String>,
     pub id_map: HashMap<String, usize>,
     pub term_map: HashMap<usize, String>,
+    /// 按词的字典序排列的词项id，用于前缀查找
+    pub sorted: Vec<usize>,
 }
 
 impl Vocabulary {
-    #[inline]
-    pub fn insert(&mut self, token: impl ToString) {
-        let word = token.to_string();
-        if self.set.insert(word.clone()) {
-            let id = self.set.len() - 1;
-            self.id_map.insert(word.clone(), id);
-            self.term_map.insert(id, word);
+    /// 按顺序插入词项，新词的id依次递增，再按字典序归并到 `sorted` 中
+    pub fn extend<T: ToString>(&mut self, tokens: impl IntoIterator<Item = T>) {
+        let mut new = vec![];
+        for token in tokens {
+            let word = token.to_string();
+            if self.set.insert(word.clone()) {
+                let id = self.set.len() - 1;
+                self.id_map.insert(word.clone(), id);
+                self.term_map.insert(id, word);
+                new.push(id);
+            }
+        }
+        if new.is_empty() {
+            return;
+        }
+
+        new.sort_by(|&a, &b| self.word(a).cmp(self.word(b)));
+        let old = std::mem::take(&mut self.sorted);
+        let mut sorted = Vec::with_capacity(old.len() + new.len());
+        let (mut i, mut j) = (0, 0);
+        while i < old.len() && j < new.len() {
+            if self.word(old[i]) < self.word(new[j]) {
+                sorted.push(old[i]);
+                i += 1;
+            } else {
+                sorted.push(new[j]);
+                j += 1;
+            }
         }
+        sorted.extend_from_slice(&old[i..]);
+        sorted.extend_from_slice(&new[j..]);
+        self.sorted = sorted;
     }
 
     #[inline]
@@ -29,6 +56,15 @@ impl Vocabulary {
         &self.term_map[&id]
     }
 
+    /// 以 `prefix` 开头的所有词项及其id，按字典序排列
+    pub fn prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = (&'a str, usize)> + 'a {
+        let start = self.sorted.partition_point(|&id| self.word(id) < prefix);
+        self.sorted[start..]
+            .iter()
+            .map(|&id| (self.word(id), id))
+            .take_while(move |(word, _)| word.starts_with(prefix))
+    }
+
     /// 词汇量
     #[inline]
     pub fn len(&self) -> usize {
@@ -1,11 +1,16 @@
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::path::PathBuf;
 
-#[derive(Debug, Clone)]
+/// 数据集目录
+const DATASET_DIR: &str = "./dataset";
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Dataset {
     pub docs: Vec<Doc>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Doc {
     pub url: String,
     pub infotype: String,
@@ -13,11 +18,88 @@ pub struct Doc {
     pub title: String,
     pub text: String,
     pub html: String,
-    pub time: String,
+    /// 发布时间，无效时为 `None`
+    #[serde(deserialize_with = "deserialize_time")]
+    pub time: Option<DateTime<Utc>>,
     pub attachments: Vec<Attachment>,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[cfg(test)]
+impl Doc {
+    /// 测试用的文档：类别为“教务”，单位为“教务部”，其他字段为空，用 `with_*` 设置
+    pub fn builder() -> Self {
+        Self {
+            url: String::new(),
+            infotype: "教务".to_owned(),
+            user: "教务部".to_owned(),
+            title: String::new(),
+            text: String::new(),
+            html: String::new(),
+            time: None,
+            attachments: vec![],
+        }
+    }
+
+    pub fn with_url(mut self, url: &str) -> Self {
+        self.url = url.to_owned();
+        self
+    }
+
+    pub fn with_infotype(mut self, infotype: &str) -> Self {
+        self.infotype = infotype.to_owned();
+        self
+    }
+
+    pub fn with_user(mut self, user: &str) -> Self {
+        self.user = user.to_owned();
+        self
+    }
+
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = title.to_owned();
+        self
+    }
+
+    pub fn with_text(mut self, text: &str) -> Self {
+        self.text = text.to_owned();
+        self
+    }
+
+    /// 发布时间，格式同数据集，无效时为 `None`
+    pub fn with_time(mut self, time: &str) -> Self {
+        self.time = parse_time(time);
+        self
+    }
+}
+
+#[cfg(test)]
+impl Dataset {
+    /// 测试用的四个文档：研究生招生、本科生选课、奖学金评选、讲座
+    pub fn sample() -> Self {
+        Self {
+            docs: vec![
+                Doc::builder()
+                    .with_url("1")
+                    .with_title("研究生招生")
+                    .with_text("研究生招生考试通知"),
+                Doc::builder()
+                    .with_url("2")
+                    .with_title("本科生选课")
+                    .with_text("选课系统开放"),
+                Doc::builder()
+                    .with_url("3")
+                    .with_title("奖学金评选")
+                    .with_text("研究生奖学金评选"),
+                Doc::builder()
+                    .with_url("4")
+                    .with_title("讲座")
+                    .with_text("人工智能讲座"),
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Attachment {
     pub name: String,
     pub url: String,
@@ -27,37 +109,102 @@ impl Dataset {
     pub async fn load() -> anyhow::Result<Self> {
         tracing::info!("[Dataset] 开始读取数据");
 
-        // recursively load ./dataset/<year>/<user>/<id>.json
         let mut docs = Vec::new();
-        let mut year_dirs = tokio::fs::read_dir("./dataset").await?;
-        while let Some(year_dir) = year_dirs.next_entry().await? {
-            if !year_dir.file_type().await?.is_dir() {
+        let mut invalid_time = 0;
+        for path in doc_files().await? {
+            let doc = tokio::fs::read_to_string(&path).await?;
+            match serde_json::from_str::<Doc>(&doc) {
+                Ok(doc) => {
+                    if doc.time.is_none() {
+                        tracing::warn!("[Dataset] 发布时间无效: {:?}", path);
+                        invalid_time += 1;
+                    }
+                    docs.push(doc)
+                }
+                Err(e) => tracing::warn!("[Dataset] 文档格式错误: {:?}: {}", path, e),
+            }
+        }
+
+        tracing::info!(
+            "[Dataset] 已读取 {} 个文档，{} 个发布时间无效",
+            docs.len(),
+            invalid_time
+        );
+
+        Ok(Self { docs })
+    }
+
+    /// 数据集指纹，由所有文档文件的路径、大小和修改时间计算，不读取文件内容
+    pub async fn fingerprint() -> anyhow::Result<u32> {
+        let mut entries = Vec::new();
+        for path in doc_files().await? {
+            let metadata = tokio::fs::metadata(&path).await?;
+            let modified = metadata
+                .modified()?
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default();
+            entries.push((path, metadata.len(), modified.as_nanos()));
+        }
+        entries.sort();
+
+        let mut hasher = crc32fast::Hasher::new();
+        for (path, len, modified) in entries {
+            hasher.update(path.to_string_lossy().as_bytes());
+            hasher.update(&len.to_le_bytes());
+            hasher.update(&modified.to_le_bytes());
+        }
+        Ok(hasher.finalize())
+    }
+}
+
+/// 公文通使用的时区（UTC+8），用于按日期、年份划分
+pub fn timezone() -> FixedOffset {
+    FixedOffset::east_opt(8 * 3600).unwrap()
+}
+
+/// 解析时间：RFC 3339（如 `2023-10-01T08:00:00.000Z`），或日期（如 `2023-10-01`，取 UTC+8 的零点）
+pub fn parse_time(s: &str) -> Option<DateTime<Utc>> {
+    let s = s.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Some(time.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()?;
+    let time = date
+        .and_hms_opt(0, 0, 0)?
+        .and_local_timezone(timezone())
+        .single()?;
+    Some(time.with_timezone(&Utc))
+}
+
+/// 读取发布时间，`null` 或无法解析时为 `None`（不影响读取文档的其他内容）
+fn deserialize_time<'de, D: Deserializer<'de>>(d: D) -> Result<Option<DateTime<Utc>>, D::Error> {
+    Ok(Option::<String>::deserialize(d)?.and_then(|s| parse_time(&s)))
+}
+
+/// 所有文档文件 ./dataset/<year>/<user>/<id>.json
+async fn doc_files() -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut year_dirs = tokio::fs::read_dir(DATASET_DIR).await?;
+    while let Some(year_dir) = year_dirs.next_entry().await? {
+        if !year_dir.file_type().await?.is_dir() {
+            continue;
+        }
+
+        let mut user_dirs = tokio::fs::read_dir(year_dir.path()).await?;
+        while let Some(user_dir) = user_dirs.next_entry().await? {
+            if !user_dir.file_type().await?.is_dir() {
                 continue;
             }
 
-            let mut user_dirs = tokio::fs::read_dir(year_dir.path()).await?;
-            while let Some(user_dir) = user_dirs.next_entry().await? {
-                if !user_dir.file_type().await?.is_dir() {
+            let mut doc_files = tokio::fs::read_dir(user_dir.path()).await?;
+            while let Some(doc_file) = doc_files.next_entry().await? {
+                if !doc_file.file_type().await?.is_file() {
                     continue;
                 }
 
-                let mut doc_files = tokio::fs::read_dir(user_dir.path()).await?;
-                while let Some(doc_file) = doc_files.next_entry().await? {
-                    if !doc_file.file_type().await?.is_file() {
-                        continue;
-                    }
-
-                    let doc = tokio::fs::read_to_string(doc_file.path()).await?;
-                    match serde_json::from_str::<Doc>(&doc) {
-                        Ok(doc) => docs.push(doc),
-                        Err(_) => println!("Error doc at {:?}", doc_file.path()),
-                    }
-                }
+                files.push(doc_file.path());
             }
         }
-
-        tracing::info!("[Dataset] 已读取 {} 个文档", docs.len());
-
-        Ok(Self { docs })
     }
+    Ok(files)
 }
@@ -7,6 +7,9 @@ use serde_json::json;
 
 pub enum AppError {
     Internal(anyhow::Error),
+    Unauthorized,
+    Forbidden,
+    NotFound,
 }
 
 // Tell axum how to convert `AppError` into a response.
@@ -16,6 +19,9 @@ impl IntoResponse for AppError {
             AppError::Internal(_) => {
                 (StatusCode::INTERNAL_SERVER_ERROR, "something went wrong")
             }
+            AppError::Unauthorized => (StatusCode::UNAUTHORIZED, "unauthorized"),
+            AppError::Forbidden => (StatusCode::FORBIDDEN, "forbidden"),
+            AppError::NotFound => (StatusCode::NOT_FOUND, "not found"),
         };
 
         let body = Json(json!({
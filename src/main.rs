@@ -1,34 +1,114 @@
+pub mod admin;
 pub mod almost_eq;
+pub mod config;
 pub mod core;
 pub mod dataset;
 pub mod error;
+pub mod merge;
 pub mod search;
+pub mod similar;
+pub mod suggest;
 
-use crate::{core::Core, dataset::Dataset};
+use crate::{
+    config::Config,
+    core::{Core, UpdateStats},
+    dataset::Dataset,
+};
 use axum::{
     routing::{get, post},
     Router,
 };
 use std::sync::Arc;
+use tokio::sync::RwLock;
 use tower_http::services::ServeDir;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub dataset: Arc<Dataset>,
-    pub core: Arc<Core>,
+    pub config: Arc<Config>,
+    /// 文档，可通过管理接口增量更新；需要同时加锁时先锁 `dataset` 再锁 `core`
+    pub dataset: Arc<RwLock<Dataset>>,
+    pub core: Arc<RwLock<Core>>,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt().with_target(false).init();
 
-    let dataset = Arc::new(Dataset::load().await?);
-    let core = Arc::new(Core::new(&dataset)?);
+    let config = Arc::new(Config::load().await?);
+
+    // 构建索引使用的线程池
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(config.threads)
+        .build_global()?;
+    tracing::info!(
+        "[Core] 构建索引使用 {} 个线程",
+        rayon::current_num_threads()
+    );
+    if config.admin_token.is_none() {
+        tracing::warn!("[Admin] 没有配置 admin_token，管理接口已禁用");
+    }
+
+    // 优先读取已保存的索引，不存在或过期时重新构建
+    let dataset_fingerprint = Dataset::fingerprint().await?;
+    let config_fingerprint = config.fingerprint();
+    let loaded = Core::load(dataset_fingerprint, config_fingerprint, &config.index_path)
+        .unwrap_or_else(|e| {
+            tracing::warn!("[Core] 读取索引失败: {:#}", e);
+            None
+        });
+    let (dataset, core) = match loaded {
+        Some((mut dataset, mut core)) => {
+            // 分析器不保存在索引中，按配置重新创建（配置变化时索引已过期），
+            // 用户词典变化时重新分词受影响的文档
+            let stats = core.reload_user_dict(&mut dataset, &config.analyzer);
+            if stats != UpdateStats::default() {
+                if let Err(e) = core.save(
+                    &dataset,
+                    dataset_fingerprint,
+                    config_fingerprint,
+                    &config.index_path,
+                ) {
+                    tracing::warn!("[Core] 保存索引失败: {:#}", e);
+                }
+            }
+            (dataset, core)
+        }
+        None => {
+            let dataset = Dataset::load().await?;
+            let core = Core::new(&dataset, &config)?;
+            if let Err(e) = core.save(
+                &dataset,
+                dataset_fingerprint,
+                config_fingerprint,
+                &config.index_path,
+            ) {
+                tracing::warn!("[Core] 保存索引失败: {:#}", e);
+            }
+            (dataset, core)
+        }
+    };
+    let dataset = Arc::new(RwLock::new(dataset));
+    let core = Arc::new(RwLock::new(core));
+
+    // 后台合并段
+    tokio::spawn(merge::run(config.clone(), dataset.clone(), core.clone()));
 
     let router = Router::new()
         .route("/health", get(|| async { "ok" }))
         .route("/search", post(search::handler))
-        .with_state(AppState { dataset, core })
+        .route("/similar/:id", get(similar::handler))
+        .route("/suggest", get(suggest::handler))
+        .route(
+            "/admin/docs",
+            post(admin::upsert_docs).delete(admin::delete_docs),
+        )
+        .route("/admin/sync", post(admin::sync))
+        .route("/admin/dict/reload", post(admin::reload_dict))
+        .with_state(AppState {
+            config,
+            dataset,
+            core,
+        })
         .fallback_service(ServeDir::new("public"));
 
     tracing::info!("监听 http://localhost:3000");
@@ -0,0 +1,64 @@
+use crate::{
+    config::Config,
+    core::{Core, Segment, TieredMergePolicy},
+    dataset::Dataset,
+};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// 后台合并段：定期按分层策略选出要合并的段，在阻塞线程中合并，完成后再短暂加写锁替换
+///
+/// 合并期间搜索和增量更新不受影响；合并期间新删除的文档仍由墓碑排除，下次合并时清除。
+/// 替换时从数据集中去掉清除的文档，其后的文档id前移
+pub async fn run(config: Arc<Config>, dataset: Arc<RwLock<Dataset>>, core: Arc<RwLock<Core>>) {
+    let policy = TieredMergePolicy::new(
+        config.merge.segments_per_tier,
+        config.merge.floor_segment_size,
+        config.merge.max_deleted_ratio,
+    );
+    let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+        config.merge.interval_secs.max(1),
+    ));
+
+    loop {
+        interval.tick().await;
+
+        // 一次检查中合并到没有可合并的段为止
+        loop {
+            let (range, sources, deleted) = {
+                let core = core.read().await;
+                let Some(range) = policy.find_merge(&core.segments, &core.deleted) else {
+                    break;
+                };
+                (
+                    range.clone(),
+                    core.segments[range].to_vec(),
+                    core.deleted.clone(),
+                )
+            };
+            tracing::info!("[Merge] 合并段 {:?}", range);
+
+            let result = tokio::task::spawn_blocking(move || {
+                let segments = sources.iter().map(|s| s.as_ref()).collect::<Vec<_>>();
+                let merged = Segment::merge(&segments, &deleted);
+                (sources, merged)
+            })
+            .await;
+            let (sources, merged) = match result {
+                Ok(result) => result,
+                Err(e) => {
+                    tracing::warn!("[Merge] 合并失败: {:#}", e);
+                    break;
+                }
+            };
+
+            let mut dataset = dataset.write().await;
+            let mut core = core.write().await;
+            if !core.replace_segments(&mut dataset, range, &sources, merged) {
+                tracing::info!("[Merge] 段已变化，放弃本次合并");
+                break;
+            }
+            tracing::info!("[Merge] 合并完成，共 {} 个段", core.segments.len());
+        }
+    }
+}
@@ -1,106 +1,676 @@
-use crate::{core::cs_helper, dataset::Doc, AppState};
+use crate::{
+    config::Config,
+    core::{
+        cs_helper, Core, Correction, Decay, DecayExplanation, DecayFunction, DocFilter, FacetField,
+        Facets, FilterIndex, HighlightOptions, Highlighter, PerField, Query, TermScore, TopK,
+    },
+    dataset::{self, Dataset, Doc},
+    AppState,
+};
 use axum::{extract::State, Json};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use sprs::CsVec;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct SearchParams {
     pub keyword: String,
     pub offset: Option<usize>,
     pub limit: Option<usize>,
     pub filter: Option<SearchParamsFilter>,
+    pub ranking: Option<Ranking>,
+    /// 结果排序方式，默认按相关度
+    pub sort: Option<Sort>,
+    /// 覆盖配置中的 BM25F 字段权重
+    pub boost: Option<PerField<Option<f64>>>,
+    /// 多个词项之间的关系，默认全部匹配
+    pub operator: Option<Operator>,
+    /// 至少匹配的词项数或百分比，指定时按 `or` 处理
+    pub minimum_should_match: Option<MinimumShouldMatch>,
+    /// 指定时返回高亮的标题和正文片段
+    pub highlight: Option<HighlightOptions>,
+    /// 覆盖配置中的时间衰减参数
+    pub decay: Option<DecayParams>,
+    /// 为 `true` 时返回每个结果得分的计算过程
+    pub explain: Option<bool>,
+    /// 没有结果时是否按纠错后的搜索词重新搜索，默认为 `true`
+    pub auto_correct: Option<bool>,
+    /// 是否统计准确的命中数和分面，默认为 `true`；
+    /// 为 `false` 且按相关度排序时跳过不可能进入当前页的文档，命中数只是下界
+    pub track_total_hits: Option<bool>,
+}
+
+/// 时间衰减参数，未指定的使用配置中的值
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+pub struct DecayParams {
+    /// 衰减函数，`none` 表示不衰减
+    pub function: Option<DecayFunction>,
+    /// 半衰期（天）
+    pub half_life_days: Option<f64>,
+    /// 衰减所占的比例, 范围 [0, 1]
+    pub weight: Option<f64>,
+}
+
+impl DecayParams {
+    /// 用搜索参数覆盖配置中的值
+    pub fn resolve(self, config: &Decay) -> Decay {
+        Decay {
+            function: self.function.unwrap_or(config.function),
+            half_life_days: self.half_life_days.unwrap_or(config.half_life_days),
+            weight: self.weight.unwrap_or(config.weight),
+        }
+    }
+}
+
+/// 多个词项之间的关系
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Operator {
+    /// 文档需包含所有词项
+    #[default]
+    And,
+    /// 文档包含任意词项即可，按得分排序
+    Or,
+}
+
+/// 至少匹配的词项数
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum MinimumShouldMatch {
+    /// 词项数，如 `2`
+    Count(usize),
+    /// 百分比，如 `"75%"`
+    Percentage(Percentage),
+}
+
+impl MinimumShouldMatch {
+    /// * `n`: 搜索词中的词项数
+    /// * `returns`: 至少匹配的词项数, 范围 [1, n]
+    pub fn resolve(self, n: usize) -> usize {
+        let count = match self {
+            MinimumShouldMatch::Count(count) => count,
+            MinimumShouldMatch::Percentage(Percentage(p)) => (n as f64 * p / 100.0) as usize,
+        };
+        count.clamp(1, n.max(1))
+    }
 }
 
+/// 百分比，序列化为 `"75%"` 的形式
+#[derive(Debug, Clone, Copy, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Percentage(pub f64);
+
+impl TryFrom<String> for Percentage {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.trim()
+            .strip_suffix('%')
+            .and_then(|p| p.trim().parse::<f64>().ok())
+            .filter(|p| (0.0..=100.0).contains(p))
+            .map(Percentage)
+            .ok_or_else(|| format!("invalid percentage: {:?}", s))
+    }
+}
+
+impl From<Percentage> for String {
+    fn from(Percentage(p): Percentage) -> Self {
+        format!("{}%", p)
+    }
+}
+
+/// 排序算法
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ranking {
+    /// TF-IDF 余弦相似度
+    #[default]
+    Tfidf,
+    /// Okapi BM25
+    Bm25,
+    /// 按字段加权的 BM25F
+    Bm25f,
+}
+
+/// 一个搜索词项的得分计算过程
 #[derive(Debug, Deserialize, Serialize)]
+pub struct TermExplanation {
+    pub term: String,
+    #[serde(flatten)]
+    pub score: TermScore,
+}
+
+/// 结果排序方式
+#[derive(Debug, Default, Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Sort {
+    /// 按得分
+    #[default]
+    Relevance,
+    /// 最新的在前，得分相同时按得分（没有发布时间的排在最后）
+    Newest,
+    /// 最早的在前
+    Oldest,
+}
+
+/// 时间范围的边界：日期（如 `"2023-09-01"`，按 UTC+8 计算）或 RFC 3339 时间
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum TimeBound {
+    Date(NaiveDate),
+    Time(DateTime<Utc>),
+}
+
+impl TimeBound {
+    /// 边界的起点，日期为当天零点
+    pub fn start(self) -> DateTime<Utc> {
+        match self {
+            TimeBound::Date(date) => dataset::parse_time(&date.to_string()).unwrap(),
+            TimeBound::Time(time) => time,
+        }
+    }
+
+    /// 边界的终点，日期为当天最后一刻
+    pub fn end(self) -> DateTime<Utc> {
+        match self {
+            TimeBound::Date(date) => {
+                TimeBound::Date(date + Duration::days(1)).start() - Duration::nanoseconds(1)
+            }
+            TimeBound::Time(time) => time,
+        }
+    }
+}
+
+impl TryFrom<String> for TimeBound {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        if let Ok(date) = NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d") {
+            return Ok(TimeBound::Date(date));
+        }
+        dataset::parse_time(&s)
+            .map(TimeBound::Time)
+            .ok_or_else(|| format!("invalid time: {:?}", s))
+    }
+}
+
+impl From<TimeBound> for String {
+    fn from(bound: TimeBound) -> Self {
+        match bound {
+            TimeBound::Date(date) => date.to_string(),
+            TimeBound::Time(time) => time.to_rfc3339(),
+        }
+    }
+}
+
+/// 一个或多个值，JSON 中可以是字符串或字符串数组，`null` 表示没有值
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(from = "OneOrMany")]
+pub struct Values(pub Vec<String>);
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany {
+    None,
+    One(String),
+    Many(Vec<String>),
+}
+
+impl From<OneOrMany> for Values {
+    fn from(values: OneOrMany) -> Self {
+        match values {
+            OneOrMany::None => Values::default(),
+            OneOrMany::One(value) => Values(vec![value]),
+            OneOrMany::Many(values) => Values(values),
+        }
+    }
+}
+
+impl From<Option<String>> for Values {
+    fn from(value: Option<String>) -> Self {
+        Values(value.into_iter().collect())
+    }
+}
+
+/// 类别、单位、发布年份（UTC+8）的条件，同一维度的多个值满足任意一个即可，没有值表示不限
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct FacetFilter {
+    pub infotype: Values,
+    pub user: Values,
+    pub year: Values,
+}
+
+impl FacetFilter {
+    /// 每个维度的值
+    pub fn values(&self) -> [(FacetField, &[String]); 3] {
+        [
+            (FacetField::Infotype, &self.infotype.0),
+            (FacetField::User, &self.user.0),
+            (FacetField::Year, &self.year.0),
+        ]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values().iter().all(|(_, values)| values.is_empty())
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
 pub struct SearchParamsFilter {
-    pub infotype: Option<String>,
-    pub user: Option<String>,
+    /// 包含的类别、单位、发布年份，如 `{"infotype": ["教务", "学术"], "year": "2023"}`
+    #[serde(flatten)]
+    pub include: FacetFilter,
+    /// 排除的类别、单位、发布年份，满足任意一个即排除
+    #[serde(default)]
+    pub exclude: FacetFilter,
+    /// 发布时间不早于（含）
+    pub from: Option<TimeBound>,
+    /// 发布时间不晚于（含），日期表示到当天结束
+    pub to: Option<TimeBound>,
+}
+
+impl SearchParamsFilter {
+    /// 文档是否满足过滤条件
+    /// * `ignore`: 忽略这个维度的条件（用于分面统计）
+    pub fn accept(&self, doc: &Doc, ignore: Option<FacetField>) -> bool {
+        let include = self.include.values();
+        let exclude = self.exclude.values();
+        for ((field, include), (_, exclude)) in include.into_iter().zip(exclude) {
+            if ignore == Some(field) || (include.is_empty() && exclude.is_empty()) {
+                continue;
+            }
+            let value = field.value(doc);
+            if !include.is_empty() && !value.as_ref().is_some_and(|v| include.contains(v)) {
+                return false;
+            }
+            if value.is_some_and(|v| exclude.contains(&v)) {
+                return false;
+            }
+        }
+        ignore == Some(FacetField::Year) || self.accept_time(doc)
+    }
+
+    /// 文档的发布时间是否在时间范围内
+    pub fn accept_time(&self, doc: &Doc) -> bool {
+        if self.from.is_none() && self.to.is_none() {
+            return true;
+        }
+        // 有时间范围时排除没有发布时间的文档
+        let Some(time) = doc.time else {
+            return false;
+        };
+        !(self.from.is_some_and(|from| time < from.start())
+            || self.to.is_some_and(|to| time > to.end()))
+    }
+
+    /// 用索引中的倒排表求出满足类别、单位、发布年份条件的文档
+    pub fn doc_filter(&self, index: &FilterIndex) -> DocFilter {
+        index.filter(&self.include.values(), &self.exclude.values())
+    }
+}
+
+/// 命中数是否准确
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TotalHitsRelation {
+    /// 等于 `total_hits`
+    #[default]
+    Eq,
+    /// 不少于 `total_hits`
+    Gte,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct SearchResult {
     pub total_hits: usize,
+    /// 命中数是否准确
+    pub total_hits_relation: TotalHitsRelation,
     pub hits: Vec<Hit>,
+    /// 命中文档中每个类别、单位、年份的数量（分页之前）
+    pub facets: Facets,
     pub time: u128,
+    /// 实际搜索的词
     pub keyword: String,
+    /// 搜索词中不在索引中的词项，搜索时被忽略
+    pub missing_terms: Vec<String>,
+    /// 不在索引中的词的纠错建议
+    pub corrections: Vec<Correction>,
+    /// 纠错后的搜索词
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub did_you_mean: Option<String>,
+    /// 原搜索词没有结果、自动按纠错后的搜索词搜索时，原来的搜索词
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_keyword: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Hit {
+    /// 文档id，替换文档或合并段后会变化，只在当前索引中有效（长期保存用 `doc.url`）
     pub id: usize,
     pub score: f64,
     pub doc: Doc,
+    /// 每个字段高亮后的文本：完整的标题、最匹配的正文片段、匹配的附件名
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlight: Option<PerField<Vec<String>>>,
+    /// 得分的计算过程
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub explain: Option<Explanation>,
+}
+
+/// 得分的计算过程，`score = text_score * decay.factor`
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Explanation {
+    pub ranking: Ranking,
+    /// 文本相关度得分
+    pub text_score: f64,
+    /// 每个搜索词项的词频、IDF、权重和对文本得分的贡献
+    pub terms: Vec<TermExplanation>,
+    /// 时间衰减，不衰减时为 `None`
+    pub decay: Option<DecayExplanation>,
 }
 
 pub async fn handler(
-    State(AppState { dataset, core }): State<AppState>,
-    Json(SearchParams {
+    State(AppState {
+        config,
+        dataset,
+        core,
+    }): State<AppState>,
+    Json(params): Json<SearchParams>,
+) -> Json<SearchResult> {
+    let dataset = dataset.read().await;
+    let core = core.read().await;
+
+    let auto_correct = params.auto_correct.unwrap_or(true);
+    let mut result = search(&config, &dataset, &core, params.clone());
+
+    // 没有结果时按纠错后的搜索词重新搜索
+    if result.total_hits == 0 && auto_correct {
+        if let Some(keyword) = result.did_you_mean.take() {
+            tracing::info!("[Search] 没有结果，改为搜索 {:?}", keyword);
+            let original_keyword = result.keyword;
+            result = search(&config, &dataset, &core, SearchParams { keyword, ..params });
+            result.original_keyword = Some(original_keyword);
+        }
+    }
+
+    Json(result)
+}
+
+fn search(
+    config: &Config,
+    dataset: &Dataset,
+    core: &Core,
+    SearchParams {
         keyword,
         offset,
         limit,
         filter,
-    }): Json<SearchParams>,
-) -> Json<SearchResult> {
+        ranking,
+        sort,
+        boost,
+        operator,
+        minimum_should_match,
+        highlight,
+        decay,
+        explain,
+        auto_correct: _,
+        track_total_hits,
+    }: SearchParams,
+) -> SearchResult {
     tracing::info!("[Search] 开始搜索: {:?}", keyword);
     let start_time = std::time::Instant::now();
 
     let limit = limit.unwrap_or(10);
     let offset = offset.unwrap_or(0);
+    let ranking = ranking.unwrap_or_default();
+    let boost = config
+        .bm25f
+        .boost
+        .map(|f, &v| boost.and_then(|b| b[f]).unwrap_or(v));
+    let decay = decay.unwrap_or_default().resolve(&config.decay);
+    if decay.is_enabled() {
+        tracing::info!("[Search] 时间衰减: {:?}", decay);
+    }
+    // 计算文档年龄的基准时间
+    let now = Utc::now();
+
+    // 解析搜索词
+    let query = Query::parse(&keyword);
+    tracing::info!("[Search] 查询: {:?}", query);
+
+    // 不在索引中的词项会被忽略，给出纠错建议
+    let positive_text = query.positive_text().join(" ");
+    let missing_terms = core.missing_terms(&positive_text);
+    let corrections = match missing_terms.is_empty() {
+        true => vec![],
+        false => core.corrections(&positive_text),
+    };
+    let did_you_mean = (!corrections.is_empty()).then(|| {
+        corrections.iter().fold(keyword.clone(), |keyword, c| {
+            keyword.replacen(&c.original, &c.suggestion, 1)
+        })
+    });
+    if !missing_terms.is_empty() {
+        tracing::info!(
+            "[Search] 不在索引中的词项: {:?}，纠错: {:?}",
+            missing_terms,
+            did_you_mean
+        );
+    }
 
-    // 搜索词词频矩阵（只有一行）
-    let search_count = core.count_vectorizer.transform(&[keyword.clone()]);
+    // 搜索词词频矩阵（只有一行），只包含用于计算得分的词
+    let search_count = core.count_vectorizer.transform(&[positive_text]);
     // 搜索词 TF-IDF 矩阵（只有一行）
     let search_tf_idf = core.tfidf_vectorizer.transform(search_count.view());
 
-    // 搜索结果（文档向量）
-    let searched_doc_vec = core.index.search(search_count.outer_view(0).unwrap());
+    // 文本相关度得分，`matched` 为文档中出现的搜索词项及其词频（来自倒排记录）
+    let text_score = |d: usize, matched: &[(usize, usize)]| {
+        // 文档所在的段
+        let (segment, i) = core.locate(d)?;
+        let score = match ranking {
+            // 计算相似度（只累加出现的搜索词项，使用预先计算的文档词数和向量模长）
+            Ranking::Tfidf => {
+                core.tfidf_vectorizer
+                    .score_doc(search_tf_idf.outer_view(0).unwrap(), d, matched)
+            }
+            // 计算 BM25 得分
+            Ranking::Bm25 => core.bm25_scorer.score(
+                search_count.outer_view(0).unwrap(),
+                segment.row(i).view(),
+                segment.field_len(i).iter().map(|(_, &len)| len).sum(),
+            ),
+            // 计算 BM25F 得分
+            Ranking::Bm25f => core.bm25f_scorer.score(
+                search_count.outer_view(0).unwrap(),
+                &segment.field_rows(i),
+                &segment.field_len(i),
+                &boost,
+            ),
+        };
+        Some(score)
+    };
 
-    // 计算得分: Vec<(d, score)>
-    let mut d_score = searched_doc_vec
-        .iter()
-        .map(|(d, _)| {
-            // 计算相似度
-            let score = cs_helper::cos_sim(
-                search_tf_idf.outer_view(0).unwrap(),
-                core.tfidf_vectorizer.get_tf_idf().outer_view(d).unwrap(),
-            );
-            (d, score)
-        })
-        .collect::<Vec<_>>();
+    // 每个搜索词项对文本得分的贡献
+    let explain_terms = |d: usize| {
+        let Some((segment, i)) = core.locate(d) else {
+            return vec![];
+        };
+        let terms = match ranking {
+            Ranking::Tfidf => core
+                .tfidf_vectorizer
+                .explain(search_tf_idf.outer_view(0).unwrap(), segment.row(i).view()),
+            Ranking::Bm25 => core.bm25_scorer.explain(
+                search_count.outer_view(0).unwrap(),
+                segment.row(i).view(),
+                segment.field_len(i).iter().map(|(_, &len)| len).sum(),
+            ),
+            Ranking::Bm25f => core.bm25f_scorer.explain(
+                search_count.outer_view(0).unwrap(),
+                &segment.field_rows(i),
+                &segment.field_len(i),
+                &boost,
+            ),
+        };
+        terms
+            .into_iter()
+            .map(|score| TermExplanation {
+                term: core.count_vectorizer.vocab().word(score.id).to_owned(),
+                score,
+            })
+            .collect()
+    };
+
+    // 文本得分乘以时间衰减的系数（不超过 1）
+    let score = |d: usize, matched: &[(usize, usize)]| {
+        Some(text_score(d, matched)? * decay.factor(dataset.docs[d].time, now))
+    };
 
-    // 按得分排序
-    d_score.sort_by(|(_, s1), (_, s2)| s2.partial_cmp(s1).unwrap());
+    // 每个搜索词项对文本得分贡献的上界
+    let bounds = match ranking {
+        Ranking::Tfidf => core.max_scores.tfidf(search_tf_idf.outer_view(0).unwrap()),
+        Ranking::Bm25 => core.max_scores.bm25(search_count.outer_view(0).unwrap()),
+        Ranking::Bm25f => core.max_scores.bm25f(
+            &core.bm25f_scorer,
+            search_count.outer_view(0).unwrap(),
+            &boost,
+        ),
+    };
+    // 搜索词项（与 `bounds` 的顺序相同）
+    let terms = bounds.iter().map(|&(t, _)| t).collect::<Vec<_>>();
 
     // 过滤
-    if let Some(filter) = filter {
-        if let Some(infotype) = &filter.infotype {
-            tracing::info!("[Search] 过滤 infotype: {:?}", infotype);
-        }
-        if let Some(user) = &filter.user {
-            tracing::info!("[Search] 过滤 user: {:?}", user);
-        }
+    let filter = filter.unwrap_or_default();
+    if !filter.include.is_empty() {
+        tracing::info!("[Search] 过滤: {:?}", filter.include);
+    }
+    if !filter.exclude.is_empty() {
+        tracing::info!("[Search] 排除: {:?}", filter.exclude);
+    }
+    if filter.from.is_some() || filter.to.is_some() {
+        tracing::info!("[Search] 过滤时间: {:?} ~ {:?}", filter.from, filter.to);
+    }
+    // 类别、单位、年份的条件在计算得分之前用倒排表求交集
+    let doc_filter = filter.doc_filter(&core.filter_index);
 
-        d_score = d_score
+    // 只有词和短语时，按 operator 和 minimum_should_match 检索
+    let x = search_count.outer_view(0).unwrap();
+    let min_match = match (operator.unwrap_or_default(), minimum_should_match) {
+        (Operator::And, None) => None,
+        (_, minimum_should_match) => Some(minimum_should_match.map_or(1, |m| m.resolve(x.nnz()))),
+    };
+    // 短语的匹配文档（忽略只有停用词的短语）
+    let phrase_docs = |phrases: Vec<&str>| {
+        phrases
             .into_iter()
-            .filter(|&(d, _)| {
-                let doc = &dataset.docs[d];
-                if let Some(infotype) = &filter.infotype {
-                    if infotype != &doc.infotype {
-                        return false;
-                    }
-                }
-                if let Some(user) = &filter.user {
-                    if user != &doc.user {
-                        return false;
+            .filter_map(|phrase| Query::Phrase(phrase.to_owned()).evaluate(core, dataset))
+            .collect::<Vec<_>>()
+    };
+
+    let sort = sort.unwrap_or_default();
+    let k = offset.saturating_add(limit);
+    let (d_score, total_hits, total_hits_relation, facets) =
+        match (track_total_hits.unwrap_or(true), sort, query.as_plain()) {
+            // 不统计准确的命中数：用词项得分上界跳过不可能进入前 k 名的文档
+            (false, Sort::Relevance, Some(phrases)) => {
+                let phrase_docs = phrase_docs(phrases);
+                let accept = |d: usize| {
+                    !core.is_deleted(d)
+                        && doc_filter.contains(d)
+                        && filter.accept_time(&dataset.docs[d])
+                        && phrase_docs
+                            .iter()
+                            .all(|docs| docs.indices().binary_search(&d).is_ok())
+                };
+                let top = core.top_k(&bounds, min_match.unwrap_or(x.nnz()), k, accept, score);
+                let relation = match top.exact {
+                    true => TotalHitsRelation::Eq,
+                    false => TotalHitsRelation::Gte,
+                };
+                (top.hits, top.total_hits, relation, Facets::default())
+            }
+            (_, sort, plain) => {
+                // 搜索结果（文档向量）
+                let searched_doc_vec = match plain {
+                    Some(phrases) => {
+                        let docs = core.search(x, None, min_match);
+                        // 短语必须匹配
+                        phrase_docs(phrases).into_iter().fold(docs, |docs, v| {
+                            cs_helper::intersection(docs.view(), v.view())
+                        })
                     }
-                }
+                    // 布尔查询，没有可以搜索的词项时没有结果
+                    None => query
+                        .evaluate(core, dataset)
+                        .unwrap_or_else(|| CsVec::new(core.num_docs, vec![], vec![])),
+                };
+                // 分面统计（每个维度忽略自身的过滤条件）
+                let facets = Facets::collect(
+                    searched_doc_vec
+                        .indices()
+                        .iter()
+                        .filter(|&&d| !core.is_deleted(d))
+                        .map(|&d| &dataset.docs[d]),
+                    |doc, field| filter.accept(doc, Some(field)),
+                );
 
-                true
-            })
-            .collect::<Vec<_>>();
-    }
+                // 与过滤条件的文档求交集，只检查剩下的文档的发布时间
+                let docs = match doc_filter.is_empty() {
+                    true => searched_doc_vec,
+                    false => doc_filter.apply(searched_doc_vec),
+                };
+                let docs = docs
+                    .indices()
+                    .iter()
+                    .copied()
+                    .filter(|&d| !core.is_deleted(d) && filter.accept_time(&dataset.docs[d]))
+                    .collect::<Vec<_>>();
+                let total_hits = docs.len();
+                // 搜索词项在每个文档中的词频，遍历倒排记录得到
+                let freqs = core.term_freqs(&terms, &docs);
 
-    // 总命中数
-    let total_hits = d_score.len();
+                // 计算得分并排序: Vec<(d, score)>
+                let by_score = |s1: &f64, s2: &f64| s2.partial_cmp(s1).unwrap();
+                let d_score = match sort {
+                    // 只保留前 k 名，得分上界达不到第 k 名的文档不计算得分
+                    Sort::Relevance => {
+                        let mut top = TopK::new(k);
+                        for (d, matched) in docs.into_iter().zip(&freqs) {
+                            let bound = bounds
+                                .iter()
+                                .filter(|&&(t, _)| matched.iter().any(|&(m, _)| m == t))
+                                .map(|&(_, ub)| ub)
+                                .sum();
+                            if top.can_skip(bound) {
+                                continue;
+                            }
+                            if let Some(score) = score(d, matched) {
+                                top.push(d, score);
+                            }
+                        }
+                        top.into_sorted_vec()
+                    }
+                    Sort::Newest | Sort::Oldest => {
+                        let mut d_score = docs
+                            .into_iter()
+                            .zip(&freqs)
+                            .filter_map(|(d, matched)| Some((d, score(d, matched)?)))
+                            .collect::<Vec<_>>();
+                        d_score.sort_by(|(d1, s1), (d2, s2)| {
+                            let (t1, t2) = (dataset.docs[*d1].time, dataset.docs[*d2].time);
+                            match sort {
+                                // Option 中 None 最小，倒序后排在最后
+                                Sort::Newest => t2.cmp(&t1),
+                                _ => t1.is_none().cmp(&t2.is_none()).then(t1.cmp(&t2)),
+                            }
+                            .then_with(|| by_score(s1, s2))
+                        });
+                        d_score
+                    }
+                };
+                (d_score, total_hits, TotalHitsRelation::Eq, facets)
+            }
+        };
 
     // 截断前 top_n 个
     let d_score = d_score
@@ -109,12 +679,32 @@ pub async fn handler(
         .take(limit)
         .collect::<Vec<_>>();
 
+    // 高亮（只处理当前页）
+    let highlighter = highlight.as_ref().map(|options| {
+        Highlighter::new(
+            &core.count_vectorizer,
+            &query.positive_text().join(" "),
+            options,
+        )
+    });
+
     let hits = d_score
         .iter()
         .map(|&(d, score)| Hit {
             id: d,
             score,
             doc: dataset.docs[d].clone(),
+            highlight: highlighter
+                .as_ref()
+                .map(|h| h.highlight_doc(&dataset.docs[d])),
+            explain: explain.unwrap_or(false).then(|| Explanation {
+                ranking,
+                text_score: text_score(d, &core.term_freqs(&terms, &[d])[0]).unwrap_or_default(),
+                terms: explain_terms(d),
+                decay: decay
+                    .is_enabled()
+                    .then(|| decay.explain(dataset.docs[d].time, now)),
+            }),
         })
         .collect::<Vec<_>>();
 
@@ -122,10 +712,84 @@ pub async fn handler(
 
     tracing::info!("[Search] 完成搜索 {:?}，耗时 {} ms", keyword, time);
 
-    Json(SearchResult {
+    SearchResult {
         total_hits,
+        total_hits_relation,
         hits,
+        facets,
         time,
         keyword,
-    })
+        missing_terms,
+        corrections,
+        did_you_mean,
+        original_keyword: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_filter() {
+        let filter: SearchParamsFilter =
+            serde_json::from_str(r#"{"from":"2023-09-01","to":"2023-09-30"}"#).unwrap();
+        assert_eq!(
+            filter.from,
+            Some(TimeBound::Date(
+                NaiveDate::from_ymd_opt(2023, 9, 1).unwrap()
+            ))
+        );
+
+        // 日期按 UTC+8 计算，结束日期包含当天
+        assert!(filter.accept(&Doc::builder().with_time("2023-09-01T00:00:00+08:00"), None));
+        assert!(filter.accept(&Doc::builder().with_time("2023-09-30T23:59:59+08:00"), None));
+        assert!(!filter.accept(&Doc::builder().with_time("2023-08-31T23:59:59+08:00"), None));
+        assert!(!filter.accept(&Doc::builder().with_time("2023-09-30T16:00:00Z"), None));
+        // 没有发布时间的文档被排除，统计年份分面时忽略时间范围
+        assert!(!filter.accept(&Doc::builder().with_time("invalid"), None));
+        assert!(filter.accept(&Doc::builder().with_time("invalid"), Some(FacetField::Year)));
+
+        let filter: SearchParamsFilter =
+            serde_json::from_str(r#"{"from":"2023-09-15T12:00:00Z"}"#).unwrap();
+        assert!(filter.accept(&Doc::builder().with_time("2023-09-15T12:00:00Z"), None));
+        assert!(!filter.accept(&Doc::builder().with_time("2023-09-15T11:59:59Z"), None));
+        assert!(serde_json::from_str::<SearchParamsFilter>(r#"{"to":"yesterday"}"#).is_err());
+    }
+
+    #[test]
+    fn test_facet_filter() {
+        // 单个值或多个值，以及排除的值
+        let filter: SearchParamsFilter = serde_json::from_str(
+            r#"{"infotype":"教务","user":["教务部","研究生院"],"exclude":{"year":"2022"}}"#,
+        )
+        .unwrap();
+        assert_eq!(filter.include.infotype, Values(vec!["教务".to_owned()]));
+        assert_eq!(filter.include.user.0.len(), 2);
+        assert!(filter.include.year.0.is_empty());
+
+        assert!(filter.accept(&Doc::builder().with_time("2023-09-01T00:00:00+08:00"), None));
+        assert!(!filter.accept(&Doc::builder().with_time("2022-09-01T00:00:00+08:00"), None));
+        // 没有发布时间的文档不被排除
+        assert!(filter.accept(&Doc::builder().with_time("invalid"), None));
+        assert!(filter.accept(
+            &Doc::builder().with_time("2022-09-01T00:00:00+08:00"),
+            Some(FacetField::Year)
+        ));
+
+        // null 表示不限
+        let filter: SearchParamsFilter = serde_json::from_str(
+            r#"{"infotype":null,"user":null,"year":null,"exclude":{"year":null}}"#,
+        )
+        .unwrap();
+        assert!(filter.include.is_empty() && filter.exclude.is_empty());
+
+        let filter: SearchParamsFilter =
+            serde_json::from_str(r#"{"user":"研究生院","year":["2023"]}"#).unwrap();
+        assert!(!filter.accept(&Doc::builder().with_time("2023-09-01T00:00:00+08:00"), None));
+        assert!(filter.accept(
+            &Doc::builder().with_time("2023-09-01T00:00:00+08:00"),
+            Some(FacetField::User)
+        ));
+    }
 }
@@ -0,0 +1,83 @@
+use crate::{
+    error::AppError,
+    search::{FacetFilter, Hit, SearchParamsFilter, TimeBound},
+    AppState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+/// 相似文档的参数（URL 查询参数）
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SimilarParams {
+    pub limit: Option<usize>,
+    /// 查询最多包含的词项数，默认 25
+    pub max_query_terms: Option<usize>,
+    pub infotype: Option<String>,
+    pub user: Option<String>,
+    pub from: Option<TimeBound>,
+    pub to: Option<TimeBound>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SimilarResult {
+    /// 作为查询的文档id
+    pub id: usize,
+    pub hits: Vec<Hit>,
+    pub time: u128,
+}
+
+/// 与文档 `id` 相似的其他文档，过滤条件与搜索相同
+pub async fn handler(
+    State(AppState { dataset, core, .. }): State<AppState>,
+    Path(id): Path<usize>,
+    Query(SimilarParams {
+        limit,
+        max_query_terms,
+        infotype,
+        user,
+        from,
+        to,
+    }): Query<SimilarParams>,
+) -> Result<Json<SimilarResult>, AppError> {
+    tracing::info!("[Similar] 开始查找相似文档: {}", id);
+    let start_time = std::time::Instant::now();
+
+    let dataset = dataset.read().await;
+    let core = core.read().await;
+
+    let filter = SearchParamsFilter {
+        include: FacetFilter {
+            infotype: infotype.into(),
+            user: user.into(),
+            ..Default::default()
+        },
+        from,
+        to,
+        ..Default::default()
+    };
+    let d_score = core
+        .similar(id, max_query_terms.unwrap_or(25), limit.unwrap_or(5), |d| {
+            filter.accept(&dataset.docs[d], None)
+        })
+        .ok_or(AppError::NotFound)?;
+
+    let hits = d_score
+        .into_iter()
+        .map(|(d, score)| Hit {
+            id: d,
+            score,
+            doc: dataset.docs[d].clone(),
+            highlight: None,
+            explain: None,
+        })
+        .collect();
+
+    let time = start_time.elapsed().as_millis();
+
+    tracing::info!("[Similar] 完成查找 {}，耗时 {} ms", id, time);
+
+    Ok(Json(SimilarResult { id, hits, time }))
+}
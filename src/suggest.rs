@@ -0,0 +1,62 @@
+use crate::{core::Suggestion, AppState};
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SuggestParams {
+    /// 用户已输入的内容，补全最后一个词
+    pub q: String,
+    /// 词项和标题片段各自最多返回的数量，默认 5
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SuggestResult {
+    pub q: String,
+    /// 补全的词项，按文档频率排列
+    pub terms: Vec<Suggestion>,
+    /// 标题中的片段，按包含它的标题数排列
+    pub titles: Vec<Suggestion>,
+    pub time: u128,
+}
+
+pub async fn handler(
+    State(AppState { core, .. }): State<AppState>,
+    Query(SuggestParams { q, limit }): Query<SuggestParams>,
+) -> Json<SuggestResult> {
+    let start_time = std::time::Instant::now();
+
+    let core = core.read().await;
+
+    let limit = limit.unwrap_or(5);
+    // 前面的词原样保留，只补全最后一个词
+    let trimmed = q.trim_end();
+    let prefix = trimmed.rsplit(char::is_whitespace).next().unwrap_or("");
+    let head = &trimmed[..trimmed.len() - prefix.len()];
+    let with_head = |suggestions: Vec<Suggestion>| {
+        suggestions
+            .into_iter()
+            .map(|s| Suggestion {
+                text: format!("{}{}", head, s.text),
+                count: s.count,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let terms = with_head(core.complete(prefix, limit));
+    let titles = with_head(core.complete_titles(prefix, limit));
+
+    let time = start_time.elapsed().as_millis();
+
+    tracing::debug!("[Suggest] {:?}，耗时 {} ms", q, time);
+
+    Json(SuggestResult {
+        q,
+        terms,
+        titles,
+        time,
+    })
+}